@@ -1,33 +1,173 @@
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 use actix_web::web::Data;
 
-use crate::blockchain::{chain::Chain, config::ChainConfig};
-use crate::storage::Storage;
+use crate::blockchain::chain::ChainEvent;
+use crate::blockchain::{chain::Chain, mempool::MempoolConfig, spec::ChainSpec};
+use crate::miner::{AutoMineConfig, AutoMiner, Miner, MiningTrigger, TemplateCache};
+use crate::p2p::{Node, NodeConfig};
+use crate::storage::{BlockStore, FileStore};
+use crate::wallet::Address;
 
-const DIFFICULTY_LEVEL: usize = 3;
-const REWARD: f64 = 10.0;
-const MINER_ADDRESS: &str = "Nebula Miner";
+const DEFAULT_CHAIN: &str = "devnet";
+const DEFAULT_STORAGE_PATH: &str = "data";
+const DEFAULT_AUTO_MINE_INTERVAL: u64 = 10;
+const DEFAULT_AUTO_MINE_THRESHOLD: usize = 10;
+const AUTO_MINE_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 pub struct AppState {
     pub app_name: String,
-    pub chain: Mutex<Chain>,
-    pub storage: Mutex<Storage>,
+    /// Network the node runs on.
+    pub spec: ChainSpec,
+    pub chain: Arc<Mutex<Chain>>,
+    pub miner: Arc<Miner>,
+    pub auto_miner: AutoMiner,
+    pub templates: Mutex<TemplateCache>,
+    /// Peer to peer node, when `P2P_ADDR` is set.
+    pub node: Option<Node>,
     pub counter: Mutex<i32>,
 }
 
 pub fn new_app_state() -> Data<AppState> {
-    let config = ChainConfig {
-        difficulty: DIFFICULTY_LEVEL,
-        reward: REWARD,
-    };
-    let chain = Chain::new(config, MINER_ADDRESS);
-    let storage = Storage {};
+    let spec = chain_spec();
+    if let Err(err) = spec.check() {
+        panic!("{err}");
+    }
+    let config = spec.config(mempool_config());
+    let miner_address = std::env::var("MINER_ADDRESS")
+        .unwrap_or_else(|_| spec.genesis.allocations[0].address.clone());
+    let miner_address = Address::parse_with_prefix(&miner_address, &config.address_prefix)
+        .expect("MINER_ADDRESS is not a valid address");
+    let storage_path =
+        std::env::var("STORAGE_PATH").unwrap_or_else(|_| DEFAULT_STORAGE_PATH.to_string());
+    let store = FileStore::open(&storage_path).expect("Unable to open block storage");
+    let loaded_from_storage = store.height() > 0;
+    let chain = Chain::new(config, &miner_address, Box::new(store));
+
+    // Refuse to serve a stored chain that has been tampered with or corrupted
+    if loaded_from_storage {
+        let report = chain.validate();
+        if let Some(failure) = report.first_failure() {
+            panic!(
+                "Stored chain failed validation with {} errors, first at {failure}",
+                report.failures.len()
+            );
+        }
+    }
+
+    let chain = Arc::new(Mutex::new(chain));
+    let miner = Arc::new(Miner::new(miner_threads()));
+
+    // Blocks from peers move the tip too, leaving jobs in progress stale
+    let events = chain.lock().unwrap().subscribe();
+    let cancelling = miner.clone();
+    thread::spawn(move || {
+        for event in events {
+            if let ChainEvent::Block(_) = event {
+                cancelling.cancel();
+            }
+        }
+    });
+
+    let node = node_config()
+        .map(|config| Node::start(config, chain.clone()).expect("Unable to listen on P2P_ADDR"));
+
+    let auto_miner = AutoMiner::new(auto_mine_config(), chain.clone(), miner.clone());
+    if std::env::var("AUTO_MINE").is_ok_and(|value| value == "true") {
+        auto_miner.start();
+    }
 
     Data::new(AppState {
         app_name: String::from("Blockchain App"),
-        chain: Mutex::new(chain),
-        storage: Mutex::new(storage),
+        spec,
+        chain,
+        miner,
+        auto_miner,
+        templates: Mutex::new(TemplateCache::new()),
+        node,
         counter: Mutex::new(0),
     })
 }
+
+/// Spec of the network to run, from the TOML or JSON file at `CHAIN_SPEC`, or
+/// else the built in network named by `CHAIN` (default `devnet`). `DEV_MODE`
+/// overrides whether the network runs in dev mode.
+fn chain_spec() -> ChainSpec {
+    let mut spec = match std::env::var("CHAIN_SPEC") {
+        Ok(path) => ChainSpec::load(&path)
+            .unwrap_or_else(|err| panic!("Unable to load CHAIN_SPEC {path}: {err}")),
+        Err(_) => {
+            let name = std::env::var("CHAIN").unwrap_or_else(|_| DEFAULT_CHAIN.to_string());
+            ChainSpec::preset(&name).unwrap_or_else(|| panic!("Unknown CHAIN {name}"))
+        }
+    };
+    if let Ok(value) = std::env::var("DEV_MODE") {
+        spec.consensus.dev_mode = value == "true";
+    }
+    spec
+}
+
+/// Mempool limits, with the minimum transfer fee from `MIN_RELAY_FEE`.
+fn mempool_config() -> MempoolConfig {
+    let mut config = MempoolConfig::default();
+    if let Ok(fee) = std::env::var("MIN_RELAY_FEE") {
+        config.min_relay_fee = fee.parse().expect("MIN_RELAY_FEE is not a valid amount");
+    }
+    config
+}
+
+/// Peer to peer settings, listening on `P2P_ADDR` and connecting to the comma
+/// separated addresses in `P2P_PEERS`. Networking is off without `P2P_ADDR`.
+fn node_config() -> Option<NodeConfig> {
+    let listen_addr = std::env::var("P2P_ADDR").ok()?;
+    let mut config = NodeConfig::new(listen_addr.parse().expect("P2P_ADDR is not an address"));
+    if let Ok(peers) = std::env::var("P2P_PEERS") {
+        config.seeds = peers
+            .split(',')
+            .filter(|peer| !peer.trim().is_empty())
+            .map(|peer| {
+                peer.trim()
+                    .parse()
+                    .expect("P2P_PEERS is not a list of addresses")
+            })
+            .collect();
+    }
+    Some(config)
+}
+
+/// Number of mining threads, from `MINER_THREADS` or the available parallelism.
+fn miner_threads() -> usize {
+    std::env::var("MINER_THREADS")
+        .ok()
+        .map(|threads| threads.parse().expect("MINER_THREADS is not a number"))
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()))
+}
+
+/// Background mining trigger from `AUTO_MINE_TRIGGER`, one of `pending`,
+/// `interval` (every `AUTO_MINE_INTERVAL` seconds) or `threshold` (once
+/// `AUTO_MINE_THRESHOLD` transactions are pending).
+fn auto_mine_config() -> AutoMineConfig {
+    let var = |name: &str| std::env::var(name).ok();
+
+    let trigger = match var("AUTO_MINE_TRIGGER").as_deref() {
+        None | Some("pending") => MiningTrigger::Pending,
+        Some("interval") => MiningTrigger::Interval {
+            secs: var("AUTO_MINE_INTERVAL").map_or(DEFAULT_AUTO_MINE_INTERVAL, |secs| {
+                secs.parse().expect("AUTO_MINE_INTERVAL is not a number")
+            }),
+        },
+        Some("threshold") => MiningTrigger::Threshold {
+            tx_count: var("AUTO_MINE_THRESHOLD").map_or(DEFAULT_AUTO_MINE_THRESHOLD, |count| {
+                count.parse().expect("AUTO_MINE_THRESHOLD is not a number")
+            }),
+        },
+        Some(other) => panic!("Unknown AUTO_MINE_TRIGGER {other}"),
+    };
+
+    AutoMineConfig {
+        trigger,
+        poll_interval: AUTO_MINE_POLL_INTERVAL,
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Number of decimal places of a coin.
+pub const DECIMALS: usize = 8;
+
+/// Number of base units in one coin.
+pub const COIN: u64 = 100_000_000;
+
+/// Amount of currency held as an integer number of base units.
+///
+/// Human readable formats such as JSON carry amounts as decimal strings with up
+/// to `DECIMALS` places, e.g. `"10.5"`, and always display all of them, e.g.
+/// `10.50000000`. Binary formats carry the raw base units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn from_base_units(units: u64) -> Self {
+        Amount(units)
+    }
+
+    pub const fn base_units(self) -> u64 {
+        self.0
+    }
+
+    pub fn from_coins(coins: u64) -> Result<Self, AmountError> {
+        coins
+            .checked_mul(COIN)
+            .map(Amount)
+            .ok_or(AmountError::Overflow)
+    }
+
+    pub fn checked_add(self, other: Amount) -> Result<Self, AmountError> {
+        self.0
+            .checked_add(other.0)
+            .map(Amount)
+            .ok_or(AmountError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Result<Self, AmountError> {
+        self.0
+            .checked_sub(other.0)
+            .map(Amount)
+            .ok_or(AmountError::Underflow)
+    }
+
+    pub fn saturating_add(self, other: Amount) -> Self {
+        Amount(self.0.saturating_add(other.0))
+    }
+
+    pub fn saturating_sub(self, other: Amount) -> Self {
+        Amount(self.0.saturating_sub(other.0))
+    }
+
+    /// Sum `amounts`, failing rather than wrapping on overflow.
+    pub fn checked_sum(amounts: impl IntoIterator<Item = Amount>) -> Result<Self, AmountError> {
+        amounts
+            .into_iter()
+            .try_fold(Amount::ZERO, |total, amount| total.checked_add(amount))
+    }
+}
+
+impl FromStr for Amount {
+    type Err = AmountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) {
+            return Err(AmountError::InvalidFormat);
+        }
+        if fraction.len() > DECIMALS {
+            return Err(AmountError::TooManyDecimals);
+        }
+
+        let whole: u64 = whole.parse().map_err(|_| AmountError::Overflow)?;
+        let fraction: u64 = format!("{fraction:0<DECIMALS$}").parse().unwrap();
+
+        Amount::from_coins(whole)?.checked_add(Amount(fraction))
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:0DECIMALS$}", self.0 / COIN, self.0 % COIN)
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u64(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let string = String::deserialize(deserializer)?;
+            string.parse().map_err(de::Error::custom)
+        } else {
+            u64::deserialize(deserializer).map(Amount)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AmountError {
+    InvalidFormat,
+    TooManyDecimals,
+    Overflow,
+    Underflow,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => write!(f, "amount must be a non-negative decimal number"),
+            Self::TooManyDecimals => write!(f, "amount has more than {DECIMALS} decimals"),
+            Self::Overflow => write!(f, "amount overflow"),
+            Self::Underflow => write!(f, "amount underflow"),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_amount() {
+        assert_eq!("10".parse(), Ok(Amount(10 * COIN)));
+        assert_eq!("10.1".parse(), Ok(Amount(1_010_000_000)));
+        assert_eq!("0.00000001".parse(), Ok(Amount(1)));
+        assert_eq!("10.".parse(), Ok(Amount(10 * COIN)));
+
+        assert_eq!("".parse::<Amount>(), Err(AmountError::InvalidFormat));
+        assert_eq!(".5".parse::<Amount>(), Err(AmountError::InvalidFormat));
+        assert_eq!("-1".parse::<Amount>(), Err(AmountError::InvalidFormat));
+        assert_eq!("1e5".parse::<Amount>(), Err(AmountError::InvalidFormat));
+        assert_eq!(
+            "0.000000001".parse::<Amount>(),
+            Err(AmountError::TooManyDecimals)
+        );
+        assert_eq!("184467440738".parse::<Amount>(), Err(AmountError::Overflow));
+    }
+
+    #[test]
+    fn display_amount() {
+        assert_eq!(Amount(1_010_000_000).to_string(), "10.10000000");
+        assert_eq!(Amount(1).to_string(), "0.00000001");
+        assert_eq!(Amount::ZERO.to_string(), "0.00000000");
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let one = Amount::from_coins(1).unwrap();
+
+        assert_eq!(one.checked_add(one), Ok(Amount(2 * COIN)));
+        assert_eq!(one.checked_sub(one), Ok(Amount::ZERO));
+        assert_eq!(Amount::ZERO.checked_sub(one), Err(AmountError::Underflow));
+        assert_eq!(
+            Amount(u64::MAX).checked_add(Amount(1)),
+            Err(AmountError::Overflow)
+        );
+        assert_eq!(Amount::checked_sum([one, one, one]), Ok(Amount(3 * COIN)));
+        assert_eq!(
+            Amount::checked_sum([Amount(u64::MAX), one]),
+            Err(AmountError::Overflow)
+        );
+    }
+
+    #[test]
+    fn serde_amount() {
+        let amount: Amount = "10.1".parse().unwrap();
+
+        assert_eq!(serde_json::to_string(&amount).unwrap(), "\"10.10000000\"");
+        assert_eq!(serde_json::from_str::<Amount>("\"10.1\"").unwrap(), amount);
+        assert!(serde_json::from_str::<Amount>("10.1").is_err());
+
+        let bytes = bincode::serialize(&amount).unwrap();
+        assert_eq!(bytes, 1_010_000_000u64.to_le_bytes());
+        assert_eq!(bincode::deserialize::<Amount>(&bytes).unwrap(), amount);
+    }
+}
@@ -1,20 +1,44 @@
-use std::collections::HashMap;
-
+use hex_fmt::HexFmt;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use super::transaction::Transaction;
+use super::amount::Amount;
+use super::target::Target;
+use super::transaction::{Transaction, TransactionType};
 use super::utils::timestamp;
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BlockHeader {
     pub index: usize,
     pub previous_hash: String,
     pub merkle_root: String,
     pub timestamp: u64,
     pub nonce: u64,
+    /// Target and reward in effect when the block was mined, both can be
+    /// changed at runtime so blocks record the values they were mined under.
+    pub target: Target,
+    pub reward: Amount,
+}
+
+impl BlockHeader {
+    /// Sha256 over the bincode serialization of every header field, so changing
+    /// any of them, including the nonce or timestamp, changes the hash.
+    pub fn hash(&self) -> String {
+        format!("{}", HexFmt(self.hash_bytes()))
+    }
+
+    pub fn hash_bytes(&self) -> [u8; 32] {
+        let bytes = bincode::serialize(self).unwrap();
+        Sha256::digest(bytes).into()
+    }
+
+    /// Whether the header hash meets the target recorded in the header.
+    pub fn meets_target(&self) -> bool {
+        self.target.is_met_by(&self.hash_bytes())
+    }
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Block {
     pub header: BlockHeader,
     pub tx_count: usize,
@@ -28,6 +52,8 @@ impl Block {
         new_txs: Vec<Transaction>,
         merkle_root: &str,
         previous_hash: &str,
+        target: Target,
+        reward: Amount,
     ) -> Self {
         let header = BlockHeader {
             index,
@@ -35,6 +61,8 @@ impl Block {
             previous_hash: previous_hash.to_string(),
             merkle_root: merkle_root.to_string(),
             timestamp: timestamp(),
+            target,
+            reward,
         };
 
         let mut txs = Vec::new();
@@ -49,6 +77,24 @@ impl Block {
             txs,
         }
     }
+
+    /// Hash of the block header, referenced by the next block's `previous_hash`.
+    pub fn hash(&self) -> String {
+        self.header.hash()
+    }
+
+    /// Bytes taken by the block once serialized, as stored and sent to peers.
+    pub fn size(&self) -> usize {
+        bincode::serialized_size(self).unwrap() as usize
+    }
+
+    /// Address paid the block reward, which is the last transaction of a block.
+    pub fn miner(&self) -> Option<&str> {
+        self.txs
+            .last()
+            .filter(|tx| tx.tx_type != TransactionType::Transfer)
+            .and_then(|tx| tx.tx_data.receiver())
+    }
 }
 
 #[cfg(test)]
@@ -63,6 +109,8 @@ mod test {
             previous_hash: "prev_hash".to_string(),
             merkle_root: "merkle_root".to_string(),
             timestamp: 3,
+            target: Target::from_difficulty(4),
+            reward: Amount::from_base_units(5),
         };
 
         assert_eq!(header.index, 1);
@@ -70,11 +118,83 @@ mod test {
         assert_eq!(header.previous_hash, "prev_hash");
         assert_eq!(header.merkle_root, "merkle_root");
         assert_eq!(header.timestamp, 3);
+        assert_eq!(header.target, Target::from_difficulty(4));
+        assert_eq!(header.reward, Amount::from_base_units(5));
+    }
+
+    #[test]
+    fn block_hash() {
+        let block = Block::new(
+            1,
+            2,
+            Vec::new(),
+            "merkle",
+            "prev_hash",
+            Target::MAX,
+            Amount::ZERO,
+        );
+        let hash = block.hash();
+
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, block.header.hash());
+
+        // Every header field is covered by the hash
+        let mut other = block.clone();
+        other.header.nonce += 1;
+        assert_ne!(other.hash(), hash);
+
+        let mut other = block.clone();
+        other.header.timestamp += 1;
+        assert_ne!(other.hash(), hash);
+
+        let mut other = block.clone();
+        other.header.previous_hash = "other_hash".to_string();
+        assert_ne!(other.hash(), hash);
+
+        let mut other = block.clone();
+        other.header.target = Target::from_difficulty(1);
+        assert_ne!(other.hash(), hash);
+
+        let mut other = block;
+        other.header.merkle_root = "other_root".to_string();
+        assert_ne!(other.hash(), hash);
+    }
+
+    #[test]
+    fn meets_target() {
+        let mut block = Block::new(
+            1,
+            0,
+            Vec::new(),
+            "merkle",
+            "prev_hash",
+            Target::MAX,
+            Amount::ZERO,
+        );
+        assert!(block.header.meets_target());
+
+        block.header.target = Target::from_difficulty(2);
+        while !block.header.meets_target() {
+            block.header.nonce += 1;
+        }
+        assert!(block.hash().starts_with("00"));
+
+        block.header.timestamp += 1;
+        block.header.target = Target::from_difficulty(64);
+        assert!(!block.header.meets_target());
     }
 
     #[test]
     fn new_block() {
-        let block = Block::new(1, 2, Vec::new(), "merkle", "prev_hash");
+        let block = Block::new(
+            1,
+            2,
+            Vec::new(),
+            "merkle",
+            "prev_hash",
+            Target::MAX,
+            Amount::ZERO,
+        );
 
         assert_eq!(block.header.index, 1);
         assert_eq!(block.header.nonce, 2);
@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use super::block::Block;
+
+/// Deepest below the tip a side branch may fork off the main chain.
+pub const MAX_FORK_DEPTH: usize = 100;
+/// Most side blocks kept at once.
+pub const MAX_SIDE_BLOCKS: usize = 1_000;
+
+/// Blocks off the main chain, by hash, each with the cumulative work of its
+/// branch from genesis.
+///
+/// Side blocks are only fully validated once their branch outweighs the main
+/// chain and the chain switches over to it.
+#[derive(Default)]
+pub struct SideBranches {
+    blocks: HashMap<String, (Block, U256)>,
+}
+
+impl SideBranches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `block` with the cumulative `work` of its branch. Past
+    /// `MAX_SIDE_BLOCKS`, the branch tip with the least work is dropped, so no
+    /// kept block loses its parent.
+    pub fn insert(&mut self, block: Block, work: U256) {
+        self.blocks.insert(block.hash(), (block, work));
+        while self.blocks.len() > MAX_SIDE_BLOCKS {
+            self.evict_lightest_tip();
+        }
+    }
+
+    pub fn remove(&mut self, hash: &str) -> Option<(Block, U256)> {
+        self.blocks.remove(hash)
+    }
+
+    pub fn get(&self, hash: &str) -> Option<(&Block, U256)> {
+        self.blocks.get(hash).map(|(block, work)| (block, *work))
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.blocks.contains_key(hash)
+    }
+
+    /// Side blocks leading up to and including `tip`, oldest first. The first
+    /// block's parent is not a side block.
+    pub fn branch(&self, tip: &str) -> Vec<&Block> {
+        let mut branch = Vec::new();
+        let mut hash = tip;
+        while let Some((block, _)) = self.blocks.get(hash) {
+            branch.push(block);
+            hash = &block.header.previous_hash;
+        }
+        branch.reverse();
+        branch
+    }
+
+    /// Drop the blocks below `height`, which are too deep to switch to.
+    pub fn prune(&mut self, height: usize) {
+        self.blocks
+            .retain(|_, (block, _)| block.header.index >= height);
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    // ---
+    // Private methods
+    // ---
+
+    fn evict_lightest_tip(&mut self) {
+        let parents: HashSet<&str> = self
+            .blocks
+            .values()
+            .map(|(block, _)| block.header.previous_hash.as_str())
+            .collect();
+        let lightest = self
+            .blocks
+            .iter()
+            .filter(|(hash, _)| !parents.contains(hash.as_str()))
+            .min_by_key(|(_, (_, work))| *work)
+            .map(|(hash, _)| hash.clone());
+        if let Some(hash) = lightest {
+            self.blocks.remove(&hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{amount::Amount, target::Target};
+
+    fn block(index: usize, previous_hash: &str) -> Block {
+        Block::new(
+            index,
+            0,
+            Vec::new(),
+            "merkle",
+            previous_hash,
+            Target::MAX,
+            Amount::ZERO,
+        )
+    }
+
+    #[test]
+    fn follow_branch() {
+        let mut side = SideBranches::new();
+        let first = block(1, "main");
+        let second = block(2, &first.hash());
+        let (first_hash, second_hash) = (first.hash(), second.hash());
+        side.insert(first, U256::from(2));
+        side.insert(second, U256::from(3));
+
+        let branch: Vec<String> = side.branch(&second_hash).iter().map(|b| b.hash()).collect();
+        assert_eq!(branch, [first_hash.clone(), second_hash.clone()]);
+        assert!(side.branch("unknown").is_empty());
+        assert_eq!(side.get(&second_hash).unwrap().1, U256::from(3));
+
+        side.prune(2);
+        assert!(!side.contains(&first_hash));
+        assert_eq!(side.branch(&second_hash).len(), 1);
+
+        side.remove(&second_hash);
+        assert!(side.is_empty());
+    }
+
+    #[test]
+    fn evict_lightest_tip() {
+        let mut side = SideBranches::new();
+        let light = block(1, "other");
+        let light_hash = light.hash();
+        side.insert(light, U256::from(20));
+
+        let mut parent_hash = "main".to_string();
+        let mut hashes = Vec::new();
+        for index in 1..=MAX_SIDE_BLOCKS {
+            let block = block(index, &parent_hash);
+            parent_hash = block.hash();
+            hashes.push(block.hash());
+            side.insert(block, U256::from(10 + index));
+        }
+
+        // The heavier branch is kept whole, though its first block has less
+        // work than the tip of the light one had
+        assert_eq!(side.len(), MAX_SIDE_BLOCKS);
+        assert!(!side.contains(&light_hash));
+        assert!(hashes.iter().all(|hash| side.contains(hash)));
+    }
+}
@@ -1,35 +1,89 @@
-use hex_fmt::HexFmt;
-use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 
-use super::block::Block;
+use primitive_types::U256;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use super::amount::Amount;
+use super::block::{Block, BlockHeader};
+use super::branches::{SideBranches, MAX_FORK_DEPTH};
 use super::config::ChainConfig;
+use super::difficulty::required_target;
+use super::error::ChainError;
 use super::hasher::{Hash, Hasher};
+use super::index::{ChainIndex, TxLocation};
+use super::mempool::Mempool;
+use super::merkle::{MerkleProof, MerkleTree};
 use super::models::TransactionData;
+use super::query::{BlockPage, BlockQuery};
+use super::state::AccountState;
+use super::target::Target;
 use super::transaction::{Transaction, TransactionStatus, TransactionType};
 use super::utils::timestamp;
+use super::validation::{
+    median_time_past, validate_block, validate_blocks, BlockError, ValidationReport,
+};
+use crate::storage::BlockStore;
+use crate::wallet::Address;
+
+/// Change made to a chain, sent to every subscriber.
+#[derive(Clone)]
+pub enum ChainEvent {
+    /// A transaction was admitted to the mempool.
+    Transaction(Transaction),
+    /// A block was appended on top of the tip.
+    Block(Block),
+}
 
-#[derive(Clone, Serialize)]
 pub struct Chain {
     config: ChainConfig,
-    miner_address: String,
-    blocks: Vec<Block>,
-    current_tx: Vec<Transaction>,
+    miner_address: Address,
+    store: Box<dyn BlockStore>,
+    mempool: Mempool,
+    accounts: AccountState,
+    index: ChainIndex,
+    // Cumulative work of the main chain up to each height
+    work: Vec<U256>,
+    side: SideBranches,
+    subscribers: Vec<Sender<ChainEvent>>,
 }
 
 impl Chain {
-    pub fn new(config: ChainConfig, miner_addr: &str) -> Self {
-        let blocks = Chain::get_blocks();
+    /// Create a chain on top of `store`, picking up any blocks and pending
+    /// transactions it already holds.
+    pub fn new(config: ChainConfig, miner_addr: &Address, store: Box<dyn BlockStore>) -> Self {
+        let pending = store
+            .load_pending()
+            .expect("Unable to load pending transactions from storage");
+        let accounts = AccountState::from_blocks(store.iter_range(0..store.height()))
+            .expect("Unable to compute account balances from stored blocks");
+
+        // Transactions no longer fitting under the pool limits are dropped
+        let mut mempool = Mempool::new(config.mempool.clone());
+        for tx in pending {
+            let fee = tx.tx_data.fee();
+            mempool.insert(tx, fee, timestamp()).ok();
+        }
+        mempool.prune(|sender| accounts.next_nonce(sender));
+
+        let index = ChainIndex::from_blocks(store.iter_range(0..store.height()));
+        let work = Chain::cumulative_work(store.iter_range(0..store.height()));
+
         let mut chain = Chain {
             config,
-            blocks,
-            miner_address: miner_addr.to_string(),
-            current_tx: Vec::new(),
+            store,
+            miner_address: miner_addr.clone(),
+            mempool,
+            accounts,
+            index,
+            work,
+            side: SideBranches::new(),
+            subscribers: Vec::new(),
         };
 
-        // TODO: REMOVE FROM CODEBASE IN PRODUCTION
-        if chain.blocks.is_empty() {
-            chain.genesis_block()
+        if chain.store.height() == 0 {
+            chain
+                .genesis_block()
+                .expect("Unable to write genesis block to storage")
         };
         chain
     }
@@ -38,207 +92,756 @@ impl Chain {
     // Public methods
     // ---
 
-    pub fn mine_new_block(&mut self) -> &Block {
-        if self.current_tx.is_empty() {
-            return self.blocks.last().unwrap();
+    /// Mine a block of the best paying pending transactions on the calling
+    /// thread.
+    pub fn mine_new_block(&mut self) -> Result<&Block, ChainError> {
+        if self.mempool.is_empty() {
+            return Ok(self.last_block());
         }
-        // Get previous block info
-        let last_block = self.blocks().last().unwrap();
-        let last_nonce = last_block.header.nonce;
-        let previous_hash = last_block.header.merkle_root.clone();
 
-        // Run proof of work algorithm
-        let nonce = self.proof_of_work(last_nonce);
+        let mut block = self.block_template();
+        Chain::proof_of_work(&mut block);
 
-        // Get new block info
-        let index = self.blocks.len();
+        self.submit_block(block)
+    }
 
-        // Create empty tx array for new block
-        let mut transactions: Vec<Transaction> = Vec::new();
+    /// Unsolved block of the best paying pending transactions, in nonce order
+    /// per sender and up to the most allowed in a block, on top of the current
+    /// tip, ready for a miner to search for a nonce.
+    pub fn block_template(&self) -> Block {
+        self.block_template_for(&self.miner_address)
+    }
 
-        // Remove all current transactions from blocks, add new new tx vec for new block
-        while !self.current_tx.is_empty() {
-            transactions.push(self.current_tx.pop().unwrap())
+    /// Block template paying the block reward to `coinbase` rather than to the
+    /// node's own miner address, for external miners.
+    pub fn block_template_for(&self, coinbase: &Address) -> Block {
+        // Copy the selected transactions into new tx vec for new block, they are
+        // only removed from the mempool once the block is stored. Leave room for
+        // the reward within the block limits
+        let limits = self.config.limits;
+        let mut transactions: Vec<Transaction> = self
+            .mempool
+            .select(limits.max_block_txs.saturating_sub(1), |sender| {
+                self.accounts.next_nonce(sender)
+            })
+            .into_iter()
+            .cloned()
+            .collect();
+
+        let block = self.assemble_block(transactions.clone(), coinbase);
+        let mut excess = block.size().saturating_sub(limits.max_block_bytes);
+        if excess == 0 {
+            return block;
         }
 
-        // Create new reward tx
-        let data = TransactionData::TransferData {
-            sender: "Root".to_string(),
-            receiver: self.miner_address.clone(),
-            amount: self.reward(),
-        };
-        let reward_tx = Chain::new_transaction(data, TransactionType::Reward);
-
-        // Add reward tx to block tx vec
-        transactions.push(reward_tx);
+        // Later transactions of a sender come after its earlier ones, so dropping
+        // from the end leaves them minable in nonce order
+        while excess > 0 {
+            let Some(tx) = transactions.pop() else {
+                break;
+            };
+            excess = excess.saturating_sub(bincode::serialized_size(&tx).unwrap() as usize);
+        }
+        self.assemble_block(transactions, coinbase)
+    }
 
-        // Change all transaction status to confirmed
-        let transactions = Chain::confirm_transactions(transactions);
+    /// Append a solved block on top of the current tip, once it passes the same
+    /// checks as full chain validation. Its transactions leave the pending set.
+    ///
+    /// A block building on another block is kept on a side branch, failing with
+    /// `StaleBlock` unless that branch now has more work than the main chain, in
+    /// which case the chain switches over to it.
+    pub fn submit_block(&mut self, block: Block) -> Result<&Block, ChainError> {
+        let hash = block.hash();
+        if self.store.get_block_by_hash(&hash).is_some() || self.side.contains(&hash) {
+            return Err(ChainError::KnownBlock);
+        }
+        if block.header.previous_hash != self.last_block().hash() {
+            return self.add_side_block(block);
+        }
 
-        // Get new merkle_root root of transactions in block
-        let merkle_root = Hasher::merkle_root(&transactions);
+        let mut accounts = self.accounts.clone();
+        let mut errors = validate_block(
+            &self.config,
+            &block,
+            self.store.height(),
+            |index| self.store.get_block(index),
+            Some(self.target()),
+            &mut accounts,
+            |tx_hash| self.index.contains(tx_hash),
+        );
+        errors.extend(self.dev_reward_error(&block));
+        if let Some(err) = errors.into_iter().next() {
+            return Err(ChainError::InvalidBlock(err));
+        }
 
-        // Make new block
-        let block = Block::new(index, nonce, transactions, &merkle_root, &previous_hash);
+        let included: HashSet<String> = block.txs.iter().map(|tx| tx.hash.to_string()).collect();
 
-        // Append block to chain
-        self.blocks.push(block);
-        self.blocks.last().unwrap()
+        // Only update balances and indexes once the block is both valid and stored
+        let work = self.work().saturating_add(block.header.target.work());
+        self.store.append_block(block)?;
+        self.accounts = accounts;
+        self.work.push(work);
+        if let Some(block) = self.store.tip() {
+            self.index.add_block(block);
+        }
+        self.side
+            .prune(self.store.height().saturating_sub(MAX_FORK_DEPTH));
+
+        self.mempool.remove(&included);
+        self.mempool
+            .prune(|sender| self.accounts.next_nonce(sender));
+        self.mempool.expire(timestamp());
+        self.store.save_pending(&self.mempool.by_arrival())?;
+
+        let tip = self.last_block().clone();
+        self.publish(ChainEvent::Block(tip));
+        Ok(self.last_block())
     }
 
     pub fn add_transaction<'a>(
         &mut self,
         tx: &'a mut Transaction,
-        sender: &str,
+        public_key: &str,
         signature: &str,
-    ) -> Result<&'a Transaction, ()> {
+    ) -> Result<&'a Transaction, ChainError> {
+        // The hash may come from a peer, and would fail every block it went into
+        if !tx.hash_matches() {
+            return Err(ChainError::TransactionHashMismatch);
+        }
+
         // Verify transaction before adding to current tx vec
-        match tx.verify(sender, signature) {
-            true => {
-                tx.status = TransactionStatus::Unconfirmed;
-                self.current_tx.push(tx.clone());
-                Ok(tx)
-            }
-            _ => Err(()),
+        tx.verify(
+            self.chain_id(),
+            &self.config.address_prefix,
+            public_key,
+            signature,
+        )
+        .map_err(ChainError::TransactionNotVerified)?;
+
+        self.check_transaction(tx)?;
+
+        tx.public_key = Some(public_key.to_string());
+        tx.signature = Some(signature.to_string());
+        tx.status = TransactionStatus::Unconfirmed;
+
+        self.mempool
+            .insert(tx.clone(), tx.tx_data.fee(), timestamp())?;
+
+        if let Err(err) = self.store.save_pending(&self.mempool.by_arrival()) {
+            self.mempool.remove(&HashSet::from([tx.hash.to_string()]));
+            return Err(err.into());
         }
+
+        self.publish(ChainEvent::Transaction(tx.clone()));
+        Ok(tx)
+    }
+
+    /// Receive every block and transaction added from now on, until the
+    /// receiver is dropped.
+    pub fn subscribe(&mut self) -> Receiver<ChainEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
     }
 
     pub fn get_transaction(&self, tx_hash: &str) -> Option<Transaction> {
-        // Find tx in current transactions
-        for curr_tx in &self.current_tx {
-            if curr_tx.hash.to_string() == tx_hash {
-                return Some(curr_tx.clone());
-            }
+        // Find tx in the mempool
+        if let Some(tx) = self.mempool.get(tx_hash) {
+            return Some(tx.clone());
         }
 
         // Find tx in blocks
-        for block in self.blocks() {
-            for tx in &block.txs {
-                if tx.hash.to_string() == tx_hash {
-                    return Some(tx.clone());
-                }
+        let location = self.index.locate(tx_hash)?;
+        self.confirmed_transaction(location).cloned()
+    }
+
+    /// Merkle proof that the confirmed transaction `tx_hash` is part of the block
+    /// it was mined in, together with that block.
+    pub fn merkle_proof(&self, tx_hash: &str) -> Option<(&Block, MerkleProof)> {
+        let location = self.index.locate(tx_hash)?;
+        let block = self.store.get_block(location.block_index)?;
+        let proof = MerkleTree::from_transactions(&block.txs).proof(location.position)?;
+        Some((block, proof))
+    }
+
+    /// Confirmed transactions sending to or from `address`, in chain order.
+    pub fn transactions_of(&self, address: &str) -> Vec<(TxLocation, &Transaction)> {
+        self.index
+            .transactions_of(address)
+            .iter()
+            .filter_map(|tx_hash| {
+                let location = self.index.locate(tx_hash)?;
+                Some((location, self.confirmed_transaction(location)?))
+            })
+            .collect()
+    }
+
+    /// Page of the blocks matching `query`, with the height the next page starts
+    /// from when the chain has more blocks to look at.
+    pub fn list_blocks(&self, query: &BlockQuery) -> BlockPage<'_> {
+        let limit = query.limit();
+        let mut blocks = Vec::new();
+
+        for height in query.heights(self.store.height()) {
+            if blocks.len() == limit {
+                return BlockPage {
+                    blocks,
+                    next: Some(height),
+                };
+            }
+
+            let block = self
+                .store
+                .get_block(height)
+                .expect("Height is in the chain");
+            if query.matches(block) {
+                blocks.push(block);
             }
         }
 
-        // No tx found
-        return None;
+        BlockPage { blocks, next: None }
+    }
+
+    /// Check every stored block, from genesis to tip.
+    pub fn validate(&self) -> ValidationReport {
+        validate_blocks(&self.config, self.store.iter_range(0..self.store.height()))
+    }
+
+    /// Spendable balance of `address`: its confirmed balance less the amounts
+    /// and fees it is paying in pending transactions.
+    pub fn balance_of(&self, address: &str) -> Result<Amount, ChainError> {
+        let confirmed = self.accounts.balance_of(address);
+        Ok(confirmed.saturating_sub(self.pending_spends(address)?))
+    }
+
+    /// Nonce the next transfer from `address` has to carry, following both its
+    /// confirmed and its pending transfers.
+    pub fn next_nonce(&self, address: &str) -> u64 {
+        let confirmed = self.accounts.next_nonce(address);
+        self.mempool
+            .next_nonce(address)
+            .map_or(confirmed, |pending| pending.max(confirmed))
     }
 
     // ---
     // Accessor methods
     // ---
 
-    pub fn blocks(&self) -> &Vec<Block> {
-        &self.blocks
+    pub fn blocks(&self) -> Vec<&Block> {
+        self.store.iter_range(0..self.store.height()).collect()
+    }
+
+    /// Number of blocks in the chain, including genesis.
+    pub fn height(&self) -> usize {
+        self.store.height()
     }
 
-    pub fn current_tx(&self) -> &Vec<Transaction> {
-        &self.current_tx
+    pub fn get_block(&self, index: usize) -> Option<&Block> {
+        self.store.get_block(index)
+    }
+
+    pub fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+        self.store.get_block_by_hash(hash)
+    }
+
+    /// Cumulative proof of work of the main chain, which is the branch with
+    /// the most work of those known.
+    pub fn work(&self) -> U256 {
+        self.work.last().copied().unwrap_or_default()
+    }
+
+    /// Block with header hash `hash`, on the main chain or a side branch.
+    pub fn known_block(&self, hash: &str) -> Option<&Block> {
+        self.store
+            .get_block_by_hash(hash)
+            .or_else(|| self.side.get(hash).map(|(block, _)| block))
+    }
+
+    /// Easiest target a block may be mined against, none in dev mode where the
+    /// difficulty is set by hand.
+    pub fn easiest_target(&self) -> Option<Target> {
+        (!self.config.dev_mode).then(|| Target::from_difficulty(self.config.difficulty))
+    }
+
+    /// Hashes of main chain blocks walking back from the tip to genesis, a
+    /// block at a time for the first ten and twice as far at each step after.
+    /// A peer looks for the first one it has to tell where our chains part.
+    pub fn locator(&self) -> Vec<String> {
+        let mut locator = Vec::new();
+        let mut index = self.store.height() - 1;
+        let mut step = 1;
+        loop {
+            if let Some(block) = self.store.get_block(index) {
+                locator.push(block.hash());
+            }
+            if index == 0 {
+                break;
+            }
+            if locator.len() >= 10 {
+                step *= 2;
+            }
+            index = index.saturating_sub(step);
+        }
+        locator
+    }
+
+    /// Headers of up to `limit` main chain blocks following the first block of
+    /// `locator` on the main chain, or from genesis if there is none.
+    pub fn headers_after(&self, locator: &[String], limit: usize) -> Vec<BlockHeader> {
+        let start = locator
+            .iter()
+            .find_map(|hash| self.store.get_block_by_hash(hash))
+            .map_or(0, |block| block.header.index + 1);
+        self.store
+            .iter_range(start..start.saturating_add(limit))
+            .map(|block| block.header.clone())
+            .collect()
+    }
+
+    pub fn last_block(&self) -> &Block {
+        self.store.tip().expect("Chain always has a genesis block")
+    }
+
+    /// Pending transactions in the order they would be mined.
+    pub fn current_tx(&self) -> Vec<&Transaction> {
+        self.mempool.iter().collect()
+    }
+
+    pub fn mempool(&self) -> &Mempool {
+        &self.mempool
     }
 
     // ---
     // Setter methods
     // ---
 
-    pub fn set_difficulty(&mut self, difficulty: usize) {
-        self.config.difficulty = difficulty
+    /// Override the difficulty, which is otherwise retargeted from block times.
+    pub fn set_difficulty(&mut self, difficulty: usize) -> Result<(), ChainError> {
+        if !self.config.dev_mode {
+            return Err(ChainError::DevModeOnly);
+        }
+        self.config.difficulty = difficulty;
+        Ok(())
+    }
+
+    /// Override the block reward, which otherwise follows the reward schedule.
+    pub fn set_reward(&mut self, reward: Amount) -> Result<(), ChainError> {
+        if !self.config.dev_mode {
+            return Err(ChainError::DevModeOnly);
+        }
+        self.config.reward = reward;
+        Ok(())
     }
 
-    pub fn set_reward(&mut self, reward: f64) {
-        self.config.reward = reward
+    /// Base reward of the next block, before fees.
+    pub fn reward(&self) -> Amount {
+        self.config.reward_at(self.store.height())
     }
 
-    pub fn reward(&self) -> f64 {
-        self.config.reward
+    /// Id of the network, which transactions are signed for.
+    pub fn chain_id(&self) -> &str {
+        &self.config.genesis.chain_id
     }
     pub fn difficulty(&self) -> usize {
         self.config.difficulty
     }
 
+    pub fn dev_mode(&self) -> bool {
+        self.config.dev_mode
+    }
+
+    /// Proof of work target the next block is mined against.
+    pub fn target(&self) -> Target {
+        if self.config.dev_mode {
+            return Target::from_difficulty(self.difficulty());
+        }
+        required_target(&self.config, self.store.height(), |index| {
+            self.store.get_block(index)
+        })
+    }
+
     // ---
     // Private methods
     // ---
 
-    fn proof_of_work(&self, last_nonce: u64) -> u64 {
-        let mut nonce: u64 = 0;
+    /// Unsolved block of `transactions` on top of the current tip, followed by
+    /// a reward paying the block reward and their fees to `coinbase`.
+    fn assemble_block(&self, mut transactions: Vec<Transaction>, coinbase: &Address) -> Block {
+        // Get previous block info
+        let previous_hash = self.last_block().hash();
+
+        // Get new block info
+        let index = self.store.height();
+
+        // Create new reward tx, paying out the fees of the included transactions.
+        // Its nonce is the block index, so rewards mined within the same second
+        // still hash differently
+        let fees = transactions.iter().map(|tx| tx.tx_data.fee());
+        let data = TransactionData::TransferData {
+            sender: "Root".to_string(),
+            receiver: coinbase.to_string(),
+            amount: fees.fold(self.reward(), Amount::saturating_add),
+            fee: Amount::ZERO,
+            nonce: index as u64,
+        };
+        let reward_tx = Chain::new_transaction(data, TransactionType::Reward);
+
+        // Add reward tx to block tx vec
+        transactions.push(reward_tx);
 
-        while !self.valid_proof(last_nonce, nonce) {
-            nonce += 1;
+        // Change all transaction status to confirmed
+        let transactions = Chain::confirm_transactions(transactions);
+
+        // Get new merkle_root root of transactions in block
+        let merkle_root = Hasher::merkle_root(&transactions);
+
+        let mut block = Block::new(
+            index,
+            0,
+            transactions,
+            &merkle_root,
+            &previous_hash,
+            self.target(),
+            self.reward(),
+        );
+
+        // Never dated before the median of recent blocks, even if they are ahead
+        // of local time
+        if let Some(median) = median_time_past(index, |index| self.store.get_block(index)) {
+            block.header.timestamp = block.header.timestamp.max(median);
         }
-        nonce
+        block
     }
 
-    fn valid_proof(&self, last_nonce: u64, nonce: u64) -> bool {
-        let guess = format!("{last_nonce:}{nonce:}");
-
-        // let mut hash_buf = [0u8; 4];
-        // let _hashed_guess = Hasher::hash_serializable(&guess, &mut hash_buf);
+    /// Keep `block` on a side branch, switching the chain over to that branch
+    /// if it now has the most work.
+    fn add_side_block(&mut self, block: Block) -> Result<&Block, ChainError> {
+        let parent_hash = &block.header.previous_hash;
+        let (parent_index, parent_work) = match self.store.get_block_by_hash(parent_hash) {
+            Some(parent) => (parent.header.index, self.work[parent.header.index]),
+            None => match self.side.get(parent_hash) {
+                Some((parent, work)) => (parent.header.index, work),
+                None => return Err(ChainError::UnknownParent),
+            },
+        };
+        if parent_index + MAX_FORK_DEPTH < self.store.height() {
+            return Err(ChainError::StaleBlock);
+        }
 
-        let hashed_guess = {
-            let bytes = bincode::serialize(&guess).unwrap();
-            // create a Sha256 object
-            let mut hasher = Sha256::new();
+        // The rest is only checked when switching branches, but the work a side
+        // block claims has to be real and no cheaper than genesis
+        if block.header.index != parent_index + 1 {
+            return Err(ChainError::InvalidBlock(BlockError::IndexMismatch {
+                expected: parent_index + 1,
+                found: block.header.index,
+            }));
+        }
+        if self
+            .easiest_target()
+            .is_some_and(|easiest| block.header.target > easiest)
+        {
+            return Err(ChainError::InvalidBlock(BlockError::TargetMismatch));
+        }
+        if !Chain::valid_proof(&block) {
+            return Err(ChainError::InvalidBlock(BlockError::InvalidProofOfWork));
+        }
 
-            // write input message
-            hasher.update(bytes);
+        let hash = block.hash();
+        let work = parent_work.saturating_add(block.header.target.work());
+        self.side.insert(block, work);
 
-            // read hash digest and consume hasher
-            let result = hasher.finalize();
+        // Ties go to the branch seen first
+        if work <= self.work() {
+            return Err(ChainError::StaleBlock);
+        }
+        self.reorganize(&hash)?;
+        Ok(self.last_block())
+    }
 
-            let str_hash = format!("{}", HexFmt(result));
-            str_hash
+    /// Switch the main chain over to the side branch ending at `tip`, once all
+    /// of its blocks are valid on top of the block it forks off from.
+    ///
+    /// Blocks taken off the main chain become a side branch themselves, and
+    /// their transactions go back to the mempool if still valid on the new
+    /// branch. Should the store fail to take the branch, the old blocks are put
+    /// back and the branch kept on the side.
+    fn reorganize(&mut self, tip: &str) -> Result<(), ChainError> {
+        let branch: Vec<Block> = self.side.branch(tip).into_iter().cloned().collect();
+        let fork = match branch.first().map(|first| &first.header.previous_hash) {
+            Some(parent_hash) => match self.store.get_block_by_hash(parent_hash) {
+                Some(parent) => parent.header.index,
+                None => return Err(ChainError::UnknownParent),
+            },
+            None => return Err(ChainError::UnknownParent),
         };
 
-        let last_chars = &hashed_guess[hashed_guess.len() - self.difficulty()..];
+        // Replay the branch on the state as of the fork
+        let mut accounts = AccountState::from_blocks(self.store.iter_range(0..fork + 1))?;
+        let mut confirmed = HashSet::new();
+        for (offset, block) in branch.iter().enumerate() {
+            let position = fork + 1 + offset;
+            let block_at = |index: usize| match index.checked_sub(fork + 1) {
+                Some(offset) => branch.get(offset),
+                None => self.store.get_block(index),
+            };
+            let required =
+                (!self.config.dev_mode).then(|| required_target(&self.config, position, block_at));
+            let mut errors = validate_block(
+                &self.config,
+                block,
+                position,
+                block_at,
+                required,
+                &mut accounts,
+                |tx_hash| {
+                    confirmed.contains(tx_hash)
+                        || self
+                            .index
+                            .locate(tx_hash)
+                            .is_some_and(|location| location.block_index <= fork)
+                },
+            );
+            errors.extend(self.dev_reward_error(block));
+
+            if let Some(err) = errors.into_iter().next() {
+                // Neither the block nor anything built on it can be switched to
+                for invalid in &branch[offset..] {
+                    self.side.remove(&invalid.hash());
+                }
+                return Err(ChainError::InvalidBlock(err));
+            }
+            confirmed.extend(block.txs.iter().map(|tx| tx.hash.to_string()));
+        }
+
+        // Take the whole branch out of the side blocks before touching the store
+        let mut connecting = Vec::with_capacity(branch.len());
+        for block in &branch {
+            match self.side.remove(&block.hash()) {
+                Some(side_block) => connecting.push(side_block),
+                None => {
+                    for (block, work) in connecting {
+                        self.side.insert(block, work);
+                    }
+                    return Err(ChainError::UnknownParent);
+                }
+            }
+        }
 
-        let mut difficulty_string = String::new();
+        let height = self.store.height();
+        let disconnected: Vec<Block> = self.store.iter_range(fork + 1..height).cloned().collect();
+        if let Err(err) = self.replace_blocks(fork, connecting.iter().map(|(block, _)| block)) {
+            // Put the main chain back, and whatever the store ends up holding,
+            // keep the rest of the state in line with it
+            self.replace_blocks(fork, &disconnected).ok();
+            for (block, work) in connecting {
+                self.side.insert(block, work);
+            }
+            self.reload_state()?;
+            return Err(err);
+        }
 
-        for _ in 0..self.difficulty() {
-            difficulty_string.push('0');
+        let disconnected_work = self.work.split_off(fork + 1);
+        self.work
+            .extend(connecting.into_iter().map(|(_, work)| work));
+        self.accounts = accounts;
+        self.index = ChainIndex::from_blocks(self.store.iter_range(0..self.store.height()));
+
+        // Disconnected transactions come before the pending ones in nonce order
+        let mut pending: Vec<Transaction> = disconnected
+            .iter()
+            .flat_map(|block| &block.txs)
+            .filter(|tx| tx.tx_type == TransactionType::Transfer)
+            .cloned()
+            .collect();
+        pending.extend(self.mempool.by_arrival());
+        for (block, work) in disconnected.into_iter().zip(disconnected_work) {
+            self.side.insert(block, work);
         }
 
-        last_chars == difficulty_string
+        self.mempool = Mempool::new(self.config.mempool.clone());
+        for mut tx in pending {
+            tx.status = TransactionStatus::Unconfirmed;
+            if self.check_transaction(&tx).is_ok() {
+                let fee = tx.tx_data.fee();
+                self.mempool.insert(tx, fee, timestamp()).ok();
+            }
+        }
+        self.store.save_pending(&self.mempool.by_arrival())?;
+
+        let connected: Vec<Block> = self
+            .store
+            .iter_range(fork + 1..self.store.height())
+            .cloned()
+            .collect();
+        for block in connected {
+            self.publish(ChainEvent::Block(block));
+        }
+        Ok(())
     }
 
-    fn get_blocks() -> Vec<Block> {
-        // TODO: GET BLOCKS FROM STORAGE
-        Vec::new()
+    /// Check that `tx` can join the mempool on top of the confirmed and pending
+    /// transactions of its sender.
+    fn check_transaction(&mut self, tx: &Transaction) -> Result<(), ChainError> {
+        let tx_hash = tx.hash.to_string();
+        if self.mempool.contains(&tx_hash) || self.index.contains(&tx_hash) {
+            return Err(ChainError::DuplicateTransaction);
+        }
+
+        // Stale transactions no longer count against the pool limits or the
+        // sender's balance
+        self.mempool.expire(timestamp());
+
+        // Only transfers may be submitted, rewards are created by the chain itself
+        if tx.tx_type != TransactionType::Transfer {
+            return Err(ChainError::InvalidTransactionType);
+        }
+
+        if let TransactionData::TransferData {
+            sender,
+            amount,
+            fee,
+            nonce,
+            ..
+        } = &tx.tx_data
+        {
+            if *amount == Amount::ZERO {
+                return Err(ChainError::InvalidAmount);
+            }
+
+            // Transfers are admitted one after another, without gaps or replays
+            let expected = self.next_nonce(sender);
+            if *nonce != expected {
+                return Err(ChainError::InvalidNonce {
+                    expected,
+                    found: *nonce,
+                });
+            }
+
+            let minimum = self.config.mempool.min_relay_fee;
+            if *fee < minimum {
+                return Err(ChainError::FeeTooLow { fee: *fee, minimum });
+            }
+
+            let available = self
+                .accounts
+                .balance_of(sender)
+                .checked_sub(self.pending_spends(sender)?)?;
+            let required = amount.checked_add(*fee)?;
+            if required > available {
+                return Err(ChainError::InsufficientFunds {
+                    available,
+                    required,
+                });
+            }
+        }
+
+        Ok(())
     }
 
-    fn genesis_block(&mut self) {
-        // Build block info
-        let nonce = 1;
-        let index = 0;
-        let previous_hash = [0; 64].iter().map(ToString::to_string).collect::<String>();
+    /// Send `event` to the subscribers, forgetting those that have gone away.
+    fn publish(&mut self, event: ChainEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
 
-        // Create empty tx array for new block
-        let mut transactions: Vec<Transaction> = Vec::new();
+    fn proof_of_work(block: &mut Block) {
+        block.header.nonce = 0;
 
-        // Create new reward tx
-        let data = TransactionData::TransferData {
-            sender: "Root".to_string(),
-            receiver: self.miner_address.clone(),
-            amount: self.reward(),
-        };
+        while !Chain::valid_proof(block) {
+            block.header.nonce += 1;
+        }
+    }
 
-        let reward_tx = Chain::new_transaction(data, TransactionType::GenesisReward);
+    /// Whether the header hash of `block` meets the target in its header.
+    pub fn valid_proof(block: &Block) -> bool {
+        block.header.meets_target()
+    }
 
-        // Add reward tx to block tx vec
-        transactions.push(reward_tx);
+    /// Append the genesis block of the spec, the same on every node.
+    fn genesis_block(&mut self) -> Result<(), ChainError> {
+        let block = self.config.genesis.block();
+        self.append_block(block)
+    }
 
-        // Update all tx status to confirmed
-        for tx in &mut transactions {
-            *tx.status = TransactionStatus::Confirmed
+    fn append_block(&mut self, block: Block) -> Result<(), ChainError> {
+        // Only update balances once the block is both valid and stored
+        let mut accounts = self.accounts.clone();
+        accounts.apply_block(&block)?;
+
+        let work = self.work().saturating_add(block.header.target.work());
+        self.store.append_block(block)?;
+        self.accounts = accounts;
+        self.work.push(work);
+        if let Some(block) = self.store.tip() {
+            self.index.add_block(block);
         }
+        Ok(())
+    }
 
-        // Get new merkle_root root of transactions in block
-        let merkle_root = Hasher::merkle_root(&transactions);
+    /// The reward can be changed at runtime in dev mode, so the stored history is
+    /// not held to it, but every new block still has to pay the current one.
+    fn dev_reward_error(&self, block: &Block) -> Option<BlockError> {
+        let expected = self.config.reward_at(block.header.index);
+        (self.config.dev_mode && block.header.reward != expected).then_some(
+            BlockError::BaseRewardMismatch {
+                expected,
+                found: block.header.reward,
+            },
+        )
+    }
+
+    /// Replace the blocks above `fork` in the store with `blocks`.
+    fn replace_blocks<'a>(
+        &mut self,
+        fork: usize,
+        blocks: impl IntoIterator<Item = &'a Block>,
+    ) -> Result<(), ChainError> {
+        self.store.truncate(fork + 1)?;
+        for block in blocks {
+            self.store.append_block(block.clone())?;
+        }
+        Ok(())
+    }
 
-        // Make new block
-        let block = Block::new(index, nonce, transactions, &merkle_root, &previous_hash);
+    /// Recompute balances, indexes and work from the stored blocks.
+    fn reload_state(&mut self) -> Result<(), ChainError> {
+        let height = self.store.height();
+        self.accounts = AccountState::from_blocks(self.store.iter_range(0..height))?;
+        self.index = ChainIndex::from_blocks(self.store.iter_range(0..height));
+        self.work = Chain::cumulative_work(self.store.iter_range(0..height));
+        Ok(())
+    }
 
-        // Append block to blocks
-        self.blocks.push(block);
+    /// Work of the chain up to and including each of `blocks`, from genesis.
+    fn cumulative_work<'a>(blocks: impl Iterator<Item = &'a Block>) -> Vec<U256> {
+        blocks
+            .scan(U256::zero(), |work, block| {
+                *work = work.saturating_add(block.header.target.work());
+                Some(*work)
+            })
+            .collect()
+    }
+
+    fn confirmed_transaction(&self, location: TxLocation) -> Option<&Transaction> {
+        self.store
+            .get_block(location.block_index)?
+            .txs
+            .get(location.position)
+    }
+
+    fn pending_spends(&self, address: &str) -> Result<Amount, ChainError> {
+        let amounts = self.mempool.iter().flat_map(|tx| match &tx.tx_data {
+            TransactionData::TransferData {
+                sender,
+                amount,
+                fee,
+                ..
+            } if sender == address => [*amount, *fee],
+            _ => [Amount::ZERO; 2],
+        });
+
+        Ok(Amount::checked_sum(amounts)?)
     }
 
     // ---
@@ -246,7 +849,14 @@ impl Chain {
     // ---
 
     pub fn new_transaction(tx_data: TransactionData, tx_type: TransactionType) -> Transaction {
-        let timestamp = timestamp();
+        Chain::new_transaction_at(tx_data, tx_type, timestamp())
+    }
+
+    pub fn new_transaction_at(
+        tx_data: TransactionData,
+        tx_type: TransactionType,
+        timestamp: u64,
+    ) -> Transaction {
         let mut hash_buf = Hash::new();
         let hash = Hasher::hash_tx_data(&tx_data, timestamp, &mut hash_buf);
         Transaction::new(tx_data, tx_type, timestamp, hash.to_owned())
@@ -264,29 +874,40 @@ impl Chain {
 mod tests {
     use test_utils::get_config;
 
+    use std::io;
+    use std::ops::Range;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
     use super::*;
-    use test_utils::{new_tx, new_tx_data};
+    use crate::blockchain::mempool::MempoolError;
+    use crate::blockchain::query::Order;
+    use crate::storage::{FileStore, MemoryStore};
+    use crate::wallet::Wallet;
+    use test_utils::{amount, miner, new_tx, new_tx_data, public_key, sender, sign};
 
     #[test]
     fn mine_block() {
         let config = get_config();
-        let mut chain = Chain::new(config, "test_miner");
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
 
         assert_eq!(chain.blocks().len(), 1);
-        chain.mine_new_block();
+        chain.mine_new_block().unwrap();
         assert_eq!(chain.blocks().len(), 1);
 
-        for _ in 0..5 {
+        for nonce in 0..5 {
+            let mut tx = new_tx(nonce);
+            let signature = sign(&tx);
             chain
-                .add_transaction(&mut new_tx(), "sender", "signature")
+                .add_transaction(&mut tx, &public_key(), &signature)
                 .unwrap();
         }
 
-        chain.mine_new_block();
+        chain.mine_new_block().unwrap();
 
-        let new_block = chain.blocks().last().unwrap();
+        let new_block = chain.last_block();
 
-        let mut reward_tx = new_tx();
+        let mut reward_tx = new_tx(0);
         let mut reward_count = 0;
         for tx in new_block.txs.iter() {
             if tx.tx_type == TransactionType::Reward {
@@ -314,21 +935,293 @@ mod tests {
     #[test]
     fn genesis_block() {
         let config = get_config();
-        let chain = Chain::new(config, "test_miner");
+        let chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
 
-        for tx in chain.blocks().last().unwrap().txs.iter() {
+        for tx in chain.last_block().txs.iter() {
             assert_eq!(tx.tx_type, TransactionType::GenesisReward);
         }
         assert_eq!(chain.blocks().len(), 1);
-        assert_eq!(chain.blocks().last().unwrap().tx_count, 1);
+        assert_eq!(chain.last_block().tx_count, 1);
+
+        // Every node builds the same genesis block from the spec
+        let other = Wallet::from_secret(&[9u8; 32]).address().clone();
+        let elsewhere = Chain::new(get_config(), &other, Box::new(MemoryStore::new()));
+        assert_eq!(elsewhere.last_block().hash(), chain.last_block().hash());
+        assert_eq!(chain.balance_of(&sender()).unwrap(), amount("12.1"));
+    }
+
+    #[test]
+    fn blocks_linked_by_hash() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        for nonce in 0..3 {
+            let mut tx = new_tx(nonce);
+            let signature = sign(&tx);
+            chain
+                .add_transaction(&mut tx, &public_key(), &signature)
+                .unwrap();
+            chain.mine_new_block().unwrap();
+        }
+
+        let blocks = chain.blocks();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(
+            blocks[0].header.previous_hash,
+            get_config().genesis.previous_hash()
+        );
+        for pair in blocks.windows(2) {
+            assert_eq!(pair[1].header.previous_hash, pair[0].hash());
+            assert_ne!(pair[1].header.previous_hash, pair[0].header.merkle_root);
+        }
+    }
+
+    #[test]
+    fn submit_block() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        let mut tx = new_tx(0);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+
+        let mut template = chain.block_template();
+        assert_eq!(template.header.index, 1);
+        assert_eq!(template.header.previous_hash, chain.last_block().hash());
+        assert_eq!(template.tx_count, 2);
+
+        // Unsolved or tampered blocks are rejected and the tx stays pending
+        template.header.target = Target::from_difficulty(64);
+        assert!(matches!(
+            chain.submit_block(template.clone()),
+            Err(ChainError::InvalidBlock(_))
+        ));
+        assert_eq!(chain.current_tx().len(), 1);
+
+        let mut block = chain.block_template();
+        Chain::proof_of_work(&mut block);
+        let mut stale = chain.block_template_for(Wallet::from_secret(&[9u8; 32]).address());
+        Chain::proof_of_work(&mut stale);
+        chain.submit_block(block.clone()).unwrap();
+
+        assert_eq!(chain.blocks().len(), 2);
+        assert!(chain.current_tx().is_empty());
+
+        // A block for a tip that has moved on no longer extends the chain
+        assert!(matches!(
+            chain.submit_block(stale),
+            Err(ChainError::StaleBlock)
+        ));
+        assert!(matches!(
+            chain.submit_block(block),
+            Err(ChainError::KnownBlock)
+        ));
+    }
+
+    #[test]
+    fn submit_block_dev_reward() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        chain.set_reward(amount("1")).unwrap();
+
+        // Paying itself more than the current reward, with a matching reward tx
+        let mut other = Chain::new(get_config(), &miner(), Box::new(MemoryStore::new()));
+        other.set_reward(amount("1000000")).unwrap();
+        let mut block = other.block_template();
+        Chain::proof_of_work(&mut block);
+        assert!(matches!(
+            chain.submit_block(block),
+            Err(ChainError::InvalidBlock(
+                BlockError::BaseRewardMismatch { .. }
+            ))
+        ));
+        assert_eq!(chain.height(), 1);
+
+        let mut block = chain.block_template();
+        Chain::proof_of_work(&mut block);
+        chain.submit_block(block).unwrap();
+        assert_eq!(chain.balance_of(&sender()).unwrap(), amount("13.1"));
+    }
+
+    #[test]
+    fn reorganize_to_heavier_branch() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let mut other = fork_of(&chain);
+
+        let mut tx = new_tx(0);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+        let replaced = chain.mine_new_block().unwrap().clone();
+        let events = chain.subscribe();
+
+        // An equally heavy branch does not replace the main chain
+        let branch = [mine_empty(&mut other), mine_empty(&mut other)];
+        assert!(matches!(
+            chain.submit_block(branch[0].clone()),
+            Err(ChainError::StaleBlock)
+        ));
+        assert_eq!(chain.last_block().hash(), replaced.hash());
+
+        // A heavier one does
+        chain.submit_block(branch[1].clone()).unwrap();
+        assert_eq!(chain.height(), 3);
+        assert_eq!(chain.last_block().hash(), branch[1].hash());
+        assert_eq!(chain.work(), other.work());
+        assert!(chain.validate().valid);
+
+        let published: Vec<String> = events
+            .try_iter()
+            .map(|event| match event {
+                ChainEvent::Block(block) => block.hash(),
+                ChainEvent::Transaction(tx) => tx.hash.to_string(),
+            })
+            .collect();
+        assert_eq!(published, [branch[0].hash(), branch[1].hash()]);
+
+        // The transfer of the replaced block is pending again
+        let tx_hash = tx.hash.to_string();
+        assert!(chain.mempool().contains(&tx_hash));
+        assert!(chain.transactions_of("you").is_empty());
+        assert_eq!(chain.next_nonce(&sender()), 1);
+
+        // The replaced block is kept as a side branch and the transfer can be
+        // mined again on the new one
+        assert!(matches!(
+            chain.submit_block(replaced),
+            Err(ChainError::KnownBlock)
+        ));
+        chain.mine_new_block().unwrap();
+        assert_eq!(chain.transactions_of("you").len(), 1);
+        assert!(chain.validate().valid);
+    }
+
+    #[test]
+    fn reject_invalid_branch() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let mut other = fork_of(&chain);
+        let tip = mine_empty(&mut chain);
+
+        let first = mine_empty(&mut other);
+        let second = mine_empty(&mut other);
+        let mut tampered = second.clone();
+        tampered.tx_count += 1;
+
+        assert!(matches!(
+            chain.submit_block(second.clone()),
+            Err(ChainError::UnknownParent)
+        ));
+        assert!(matches!(
+            chain.submit_block(first),
+            Err(ChainError::StaleBlock)
+        ));
+        assert!(matches!(
+            chain.submit_block(tampered),
+            Err(ChainError::InvalidBlock(BlockError::TxCountMismatch { .. }))
+        ));
+        assert_eq!(chain.last_block().hash(), tip.hash());
+
+        // The valid part of the branch is still there to build on
+        chain.submit_block(second.clone()).unwrap();
+        assert_eq!(chain.last_block().hash(), second.hash());
+    }
+
+    #[test]
+    fn restore_chain_on_failed_reorganize() {
+        let fail = Arc::new(AtomicBool::new(false));
+        let store = FailingStore {
+            inner: MemoryStore::new(),
+            fail: fail.clone(),
+        };
+        let mut chain = Chain::new(get_config(), &miner(), Box::new(store));
+        let mut other = fork_of(&chain);
+
+        let mut tx = new_tx(0);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+        let tip = chain.mine_new_block().unwrap().clone();
+        let balance = chain.balance_of(&sender()).unwrap();
+
+        let branch = [
+            mine_empty(&mut other),
+            mine_empty(&mut other),
+            mine_empty(&mut other),
+        ];
+        assert!(matches!(
+            chain.submit_block(branch[0].clone()),
+            Err(ChainError::StaleBlock)
+        ));
+        fail.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            chain.submit_block(branch[1].clone()),
+            Err(ChainError::Storage(_))
+        ));
+
+        // The main chain is put back and the branch kept to switch to later
+        assert_eq!(chain.height(), 2);
+        assert_eq!(chain.last_block().hash(), tip.hash());
+        assert_eq!(chain.balance_of(&sender()).unwrap(), balance);
+        assert_eq!(chain.transactions_of("you").len(), 1);
+        assert!(chain.validate().valid);
+
+        chain.submit_block(branch[2].clone()).unwrap();
+        assert_eq!(chain.last_block().hash(), branch[2].hash());
+        assert_eq!(chain.work(), other.work());
+        assert!(chain.validate().valid);
+    }
+
+    #[test]
+    fn block_template_for() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let coinbase = Wallet::from_secret(&[9u8; 32]).address().clone();
+
+        let mut block = chain.block_template_for(&coinbase);
+        Chain::proof_of_work(&mut block);
+        chain.submit_block(block).unwrap();
+
+        assert_eq!(
+            chain.balance_of(&coinbase.to_string()).unwrap(),
+            amount("12.1")
+        );
+    }
+
+    #[test]
+    fn validate_chain() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        for nonce in 0..2 {
+            let mut tx = new_tx(nonce);
+            let signature = sign(&tx);
+            chain
+                .add_transaction(&mut tx, &public_key(), &signature)
+                .unwrap();
+            chain.mine_new_block().unwrap();
+        }
+
+        // Blocks keep validating after the configured values change
+        chain.set_reward(amount("1")).unwrap();
+        chain.set_difficulty(2).unwrap();
+
+        let report = chain.validate();
+        assert!(report.valid);
+        assert_eq!(report.height, 3);
     }
 
     #[test]
     fn confirm_transactions() {
         let mut txs: Vec<Transaction> = Vec::new();
 
-        for _ in 0..5 {
-            txs.push(new_tx());
+        for nonce in 0..5 {
+            txs.push(new_tx(nonce));
         }
 
         let confirmed_txs = Chain::confirm_transactions(txs);
@@ -341,37 +1234,181 @@ mod tests {
     #[test]
     fn add_transaction() {
         let config = get_config();
-        let mut chain = Chain::new(config, "test_miner");
-        let tx_data = new_tx_data(12.1);
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let tx_data = new_tx_data("2.1", 0);
         let mut tx1 = Chain::new_transaction(tx_data, TransactionType::Transfer);
+        let signature = sign(&tx1);
 
         chain
-            .add_transaction(&mut tx1, "sender", "signature")
+            .add_transaction(&mut tx1, &public_key(), &signature)
             .unwrap();
 
         assert_eq!(tx1.status, TransactionStatus::Unconfirmed);
+        assert_eq!(tx1.signature, Some(signature));
         assert_eq!(chain.current_tx().len(), 1);
 
-        let tx_data = new_tx_data(11.1);
+        let tx_data = new_tx_data("1.1", 1);
         let mut tx2 = Chain::new_transaction(tx_data, TransactionType::Transfer);
+        let signature = sign(&tx2);
 
         chain
-            .add_transaction(&mut tx2, "sender", "signature")
+            .add_transaction(&mut tx2, &public_key(), &signature)
             .unwrap();
 
         assert_eq!(tx2.status, TransactionStatus::Unconfirmed);
         assert_eq!(chain.current_tx().len(), 2);
     }
 
+    #[test]
+    fn transaction_nonces() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        assert_eq!(chain.next_nonce(&sender()), 0);
+
+        let mut tx = new_tx(0);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+        assert_eq!(chain.next_nonce(&sender()), 1);
+
+        // Gaps are rejected as well as replays
+        let mut gap = new_tx(2);
+        let gap_signature = sign(&gap);
+        assert!(matches!(
+            chain.add_transaction(&mut gap, &public_key(), &gap_signature),
+            Err(ChainError::InvalidNonce {
+                expected: 1,
+                found: 2
+            })
+        ));
+
+        // A new transfer reusing a confirmed nonce
+        chain.mine_new_block().unwrap();
+        assert_eq!(chain.next_nonce(&sender()), 1);
+        let mut reused = Chain::new_transaction(new_tx_data("1", 0), TransactionType::Transfer);
+        let signature = sign(&reused);
+        assert!(matches!(
+            chain.add_transaction(&mut reused, &public_key(), &signature),
+            Err(ChainError::InvalidNonce {
+                expected: 1,
+                found: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn duplicate_transaction() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        let tx = new_tx(0);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx.clone(), &public_key(), &signature)
+            .unwrap();
+        assert!(matches!(
+            chain.add_transaction(&mut tx.clone(), &public_key(), &signature),
+            Err(ChainError::DuplicateTransaction)
+        ));
+
+        chain.mine_new_block().unwrap();
+        assert!(matches!(
+            chain.add_transaction(&mut tx.clone(), &public_key(), &signature),
+            Err(ChainError::DuplicateTransaction)
+        ));
+
+        // Rewards of blocks mined within the same second stay distinct
+        for nonce in 1..3 {
+            let mut tx = new_tx(nonce);
+            let signature = sign(&tx);
+            chain
+                .add_transaction(&mut tx, &public_key(), &signature)
+                .unwrap();
+            chain.mine_new_block().unwrap();
+        }
+        assert!(chain.validate().valid);
+    }
+
+    #[test]
+    fn mempool_limits() {
+        let mut config = get_config();
+        config.mempool.max_count = 2;
+        config.limits.max_block_txs = 2;
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        for (nonce, amount) in [(0, "1"), (1, "2")] {
+            let mut tx =
+                Chain::new_transaction(new_tx_data(amount, nonce), TransactionType::Transfer);
+            let signature = sign(&tx);
+            chain
+                .add_transaction(&mut tx, &public_key(), &signature)
+                .unwrap();
+        }
+
+        let mut tx = Chain::new_transaction(new_tx_data("3", 2), TransactionType::Transfer);
+        let signature = sign(&tx);
+        assert!(matches!(
+            chain.add_transaction(&mut tx, &public_key(), &signature),
+            Err(ChainError::Mempool(MempoolError::Full))
+        ));
+
+        // Only the first to arrive fits in the block
+        let first = chain.current_tx()[0].hash.to_string();
+        let block = chain.mine_new_block().unwrap();
+        assert_eq!(block.tx_count, 2);
+        assert_eq!(block.txs[0].hash.to_string(), first);
+        assert_eq!(chain.current_tx().len(), 1);
+    }
+
+    #[test]
+    fn block_limits() {
+        let mut config = get_config();
+        config.limits.max_block_txs = 3;
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        for nonce in 0..4 {
+            let mut tx = new_tx(nonce);
+            let signature = sign(&tx);
+            chain
+                .add_transaction(&mut tx, &public_key(), &signature)
+                .unwrap();
+        }
+
+        // Two transfers and the reward
+        let block = chain.block_template();
+        assert_eq!(block.tx_count, 3);
+
+        // Only as many transfers as fit in the bytes left over
+        let tx_size = bincode::serialized_size(&chain.current_tx()[0]).unwrap() as usize;
+        chain.config.limits.max_block_bytes = block.size() - 1;
+        let smaller = chain.block_template();
+        assert_eq!(smaller.tx_count, 2);
+        assert!(smaller.size() <= block.size() - tx_size);
+
+        // Blocks over the limits are refused
+        chain.config.limits.max_block_txs = 2;
+        let mut block = block;
+        Chain::proof_of_work(&mut block);
+        assert!(matches!(
+            chain.submit_block(block),
+            Err(ChainError::InvalidBlock(BlockError::TooManyTransactions {
+                max: 2,
+                found: 3
+            }))
+        ));
+    }
+
     #[test]
     fn get_transaction() {
         let config = get_config();
-        let mut chain = Chain::new(config, "test_miner");
-        let tx_data = new_tx_data(12.1);
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let tx_data = new_tx_data("12.1", 0);
         let mut tx = Chain::new_transaction(tx_data, TransactionType::Transfer);
+        let signature = sign(&tx);
 
         chain
-            .add_transaction(&mut tx, "sender", "signature")
+            .add_transaction(&mut tx, &public_key(), &signature)
             .unwrap();
 
         let tx_from_chain = chain.get_transaction(&tx.hash.to_string()).unwrap();
@@ -384,89 +1421,622 @@ mod tests {
             _ => panic!("Should not be found"),
         }
     }
+    #[test]
+    fn add_transaction_not_verified() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let mut tx = new_tx(0);
+
+        let res = chain.add_transaction(&mut tx, &public_key(), "signature");
+
+        assert!(matches!(res, Err(ChainError::TransactionNotVerified(_))));
+        assert!(chain.current_tx().is_empty());
+    }
+
+    #[test]
+    fn add_transaction_hash_mismatch() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let mut tx = new_tx(0);
+        let signature = sign(&tx);
+        tx.hash = Chain::new_transaction(new_tx_data("2", 0), TransactionType::Transfer).hash;
+
+        let res = chain.add_transaction(&mut tx, &public_key(), &signature);
+
+        assert!(matches!(res, Err(ChainError::TransactionHashMismatch)));
+        assert!(chain.current_tx().is_empty());
+        chain.mine_new_block().unwrap();
+    }
+
+    #[test]
+    fn add_transaction_insufficient_funds() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        // Genesis reward of 12.1 less a pending spend of 10.0
+        let mut tx = Chain::new_transaction(new_tx_data("10", 0), TransactionType::Transfer);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+        assert_eq!(
+            chain.balance_of(&miner().to_string()).unwrap(),
+            amount("2.1")
+        );
+
+        let mut tx = Chain::new_transaction(new_tx_data("3", 1), TransactionType::Transfer);
+        let signature = sign(&tx);
+        let res = chain.add_transaction(&mut tx, &public_key(), &signature);
+
+        assert!(matches!(res, Err(ChainError::InsufficientFunds { .. })));
+        assert_eq!(chain.current_tx().len(), 1);
+    }
+
+    #[test]
+    fn add_transaction_invalid() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        let mut tx = Chain::new_transaction(new_tx_data("0", 0), TransactionType::Transfer);
+        let signature = sign(&tx);
+        let res = chain.add_transaction(&mut tx, &public_key(), &signature);
+        assert!(matches!(res, Err(ChainError::InvalidAmount)));
+
+        let mut tx = Chain::new_transaction(new_tx_data("1", 0), TransactionType::Reward);
+        let signature = sign(&tx);
+        let res = chain.add_transaction(&mut tx, &public_key(), &signature);
+        assert!(matches!(res, Err(ChainError::InvalidTransactionType)));
+    }
+
+    #[test]
+    fn balance_of() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let miner = miner().to_string();
+
+        assert_eq!(chain.balance_of(&miner).unwrap(), amount("12.1"));
+        assert_eq!(chain.balance_of("you").unwrap(), Amount::ZERO);
+
+        let mut tx = Chain::new_transaction(new_tx_data("2", 0), TransactionType::Transfer);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+
+        // Pending receipts are not spendable until mined
+        assert_eq!(chain.balance_of(&miner).unwrap(), amount("10.1"));
+        assert_eq!(chain.balance_of("you").unwrap(), Amount::ZERO);
+
+        chain.mine_new_block().unwrap();
+
+        assert_eq!(chain.balance_of(&miner).unwrap(), amount("22.2"));
+        assert_eq!(chain.balance_of("you").unwrap(), amount("2"));
+
+        // Pending spends too large to add up are an error, not a zero balance
+        for nonce in 1..3 {
+            let mut data = new_tx_data("0", nonce);
+            if let TransactionData::TransferData { amount, .. } = &mut data {
+                *amount = Amount::from_base_units(u64::MAX);
+            }
+            let tx = Chain::new_transaction(data, TransactionType::Transfer);
+            chain.mempool.insert(tx, Amount::ZERO, timestamp()).unwrap();
+        }
+        assert!(matches!(
+            chain.balance_of(&miner),
+            Err(ChainError::Amount(_))
+        ));
+    }
+
+    #[test]
+    fn transaction_fees() {
+        let mut config = get_config();
+        config.mempool.min_relay_fee = amount("0.1");
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let coinbase = Wallet::from_secret(&[9u8; 32]).address().clone();
+
+        let mut tx = Chain::new_transaction(new_tx_data("2", 0), TransactionType::Transfer);
+        let signature = sign(&tx);
+        assert!(matches!(
+            chain.add_transaction(&mut tx, &public_key(), &signature),
+            Err(ChainError::FeeTooLow { .. })
+        ));
+
+        let tx_data = TransactionData::TransferData {
+            sender: miner().to_string(),
+            receiver: "you".to_string(),
+            amount: amount("2"),
+            fee: amount("0.5"),
+            nonce: 0,
+        };
+        let mut tx = Chain::new_transaction(tx_data, TransactionType::Transfer);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+        assert_eq!(
+            chain.balance_of(&miner().to_string()).unwrap(),
+            amount("9.6")
+        );
+
+        let mut block = chain.block_template_for(&coinbase);
+        Chain::proof_of_work(&mut block);
+        chain.submit_block(block).unwrap();
+
+        assert_eq!(
+            chain.balance_of(&miner().to_string()).unwrap(),
+            amount("9.6")
+        );
+        assert_eq!(chain.balance_of("you").unwrap(), amount("2"));
+        assert_eq!(
+            chain.balance_of(&coinbase.to_string()).unwrap(),
+            amount("12.6")
+        );
+        assert!(chain.validate().valid);
+    }
+
+    #[test]
+    fn merkle_proof() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        let mut hashes = Vec::new();
+        for nonce in 0..3 {
+            let mut tx = new_tx(nonce);
+            let signature = sign(&tx);
+            chain
+                .add_transaction(&mut tx, &public_key(), &signature)
+                .unwrap();
+            hashes.push(tx.hash.to_string());
+        }
+
+        // Pending transactions have no proof yet
+        assert!(chain.merkle_proof(&hashes[0]).is_none());
+
+        chain.mine_new_block().unwrap();
+
+        for hash in &hashes {
+            let (block, proof) = chain.merkle_proof(hash).unwrap();
+            assert_eq!(block.header.index, 1);
+            assert!(proof.verify(hash, &block.header.merkle_root));
+        }
+        assert!(chain.merkle_proof("not found").is_none());
+    }
+
+    #[test]
+    fn indexed_lookups() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        let mut tx = new_tx(0);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+        let block = chain.mine_new_block().unwrap().clone();
+
+        assert_eq!(chain.get_block(1).unwrap().hash(), block.hash());
+        assert_eq!(
+            chain.get_block_by_hash(&block.hash()).unwrap().header.index,
+            1
+        );
+        assert!(chain.get_block(2).is_none());
+
+        let found = chain.transactions_of("you");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.block_index, 1);
+        assert_eq!(found[0].1.hash.to_string(), tx.hash.to_string());
+
+        // Genesis reward, the transfer and the reward of the block
+        assert_eq!(chain.transactions_of(&sender()).len(), 3);
+    }
+
+    #[test]
+    fn locate_headers() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        for _ in 0..14 {
+            mine_empty(&mut chain);
+        }
+
+        // Ten steps back from the tip, then doubling down to genesis
+        let hash = |index| chain.get_block(index).unwrap().hash();
+        let locator = chain.locator();
+        let expected: Vec<String> = [14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 3, 0]
+            .into_iter()
+            .map(hash)
+            .collect();
+        assert_eq!(locator, expected);
+        assert!(chain.headers_after(&locator, 10).is_empty());
+
+        // A peer that only shares genesis gets headers from height 1 on
+        let mut other = fork_of(&chain);
+        let side = mine_empty(&mut other);
+        let headers = chain.headers_after(&other.locator(), 5);
+        let indexes: Vec<usize> = headers.iter().map(|header| header.index).collect();
+        assert_eq!(indexes, [1, 2, 3, 4, 5]);
+        assert_eq!(headers[0].hash(), hash(1));
+        assert_eq!(chain.headers_after(&[], 2)[0].hash(), hash(0));
+
+        // Side blocks are known, though not on the main chain
+        assert!(matches!(
+            chain.submit_block(side.clone()),
+            Err(ChainError::StaleBlock)
+        ));
+        assert!(chain.get_block_by_hash(&side.hash()).is_none());
+        assert_eq!(chain.known_block(&side.hash()).unwrap().hash(), side.hash());
+        assert!(chain.known_block("unknown").is_none());
+    }
+
     #[test]
     #[should_panic]
     fn get_transaction_not_found() {
         let config = get_config();
-        let chain = Chain::new(config, "test_miner");
+        let chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
 
         chain.get_transaction("not found").unwrap();
     }
 
+    #[test]
+    fn load_chain_from_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::open(dir.path()).unwrap();
+        let mut chain = Chain::new(get_config(), &miner(), Box::new(store));
+
+        let mut tx = new_tx(0);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+        chain.mine_new_block().unwrap();
+        let mined_hash = tx.hash.to_string();
+
+        let mut tx = new_tx(1);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+
+        let mined_root = chain.last_block().header.merkle_root.clone();
+        drop(chain);
+
+        let store = FileStore::open(dir.path()).unwrap();
+        let chain = Chain::new(get_config(), &miner(), Box::new(store));
+
+        assert_eq!(chain.blocks().len(), 2);
+        assert_eq!(chain.last_block().header.merkle_root, mined_root);
+        assert_eq!(chain.current_tx().len(), 1);
+
+        // Indexes are rebuilt from the stored blocks
+        assert_eq!(chain.transactions_of("you").len(), 1);
+        assert!(chain.get_transaction(&mined_hash).is_some());
+    }
+
     #[test]
     fn new_chain() {
         let config = get_config();
-        let chain = Chain::new(config, "test_miner");
+        let chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
 
-        assert_eq!(chain.miner_address, "test_miner");
+        assert_eq!(chain.miner_address, miner());
         assert_eq!(chain.difficulty(), 0);
-        assert_eq!(chain.reward(), 12.1);
+        assert_eq!(chain.reward(), amount("12.1"));
     }
 
     #[test]
     fn set_difficulty() {
         let config = get_config();
-        let mut chain = Chain::new(config, "test_miner");
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
 
-        chain.set_difficulty(1);
+        chain.set_difficulty(1).unwrap();
 
         assert_eq!(chain.difficulty(), 1);
+        assert_eq!(chain.target(), Target::from_difficulty(1));
+
+        // Outside dev mode the difficulty follows block times
+        let mut config = get_config();
+        config.dev_mode = false;
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        assert!(matches!(
+            chain.set_difficulty(1),
+            Err(ChainError::DevModeOnly)
+        ));
+        assert_eq!(chain.difficulty(), 0);
+    }
+
+    #[test]
+    fn retarget_difficulty() {
+        let mut config = get_config();
+        config.difficulty = 1;
+        config.dev_mode = false;
+        config.retarget_window = 2;
+        // Genesis mined just now as well
+        config.genesis.difficulty = 1;
+        config.genesis.timestamp = timestamp();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        for nonce in 0..3 {
+            let mut tx = new_tx(nonce);
+            let signature = sign(&tx);
+            chain
+                .add_transaction(&mut tx, &public_key(), &signature)
+                .unwrap();
+            chain.mine_new_block().unwrap();
+        }
+
+        // Blocks mined within the same second are far too fast, so every
+        // window makes the next blocks harder
+        let targets: Vec<Target> = chain.blocks().iter().map(|b| b.header.target).collect();
+        assert_eq!(targets[0], Target::from_difficulty(1));
+        assert_eq!(targets[1], targets[0]);
+        assert!(targets[2] < targets[1]);
+        assert_eq!(targets[3], targets[2]);
+        assert!(chain.validate().valid);
     }
 
     #[test]
     fn get_blocks() {
         let config = get_config();
-        let chain = Chain::new(config, "test_miner");
+        let chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
 
         let blocks = chain.blocks();
 
         assert_ne!(blocks.len(), 0);
     }
 
+    #[test]
+    fn list_blocks() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let coinbase = Wallet::from_secret(&[9u8; 32]).address().clone();
+
+        // Blocks 1 and 3 are mined by the node, 2 and 4 by the coinbase
+        for _ in 0..2 {
+            let mut tx = new_tx(chain.next_nonce(&sender()));
+            let signature = sign(&tx);
+            chain
+                .add_transaction(&mut tx, &public_key(), &signature)
+                .unwrap();
+            chain.mine_new_block().unwrap();
+
+            let mut block = chain.block_template_for(&coinbase);
+            Chain::proof_of_work(&mut block);
+            chain.submit_block(block).unwrap();
+        }
+
+        let heights = |page: BlockPage| -> Vec<usize> {
+            page.blocks.iter().map(|block| block.header.index).collect()
+        };
+
+        let page = chain.list_blocks(&BlockQuery {
+            limit: 2,
+            ..BlockQuery::default()
+        });
+        assert_eq!(page.next, Some(2));
+        assert_eq!(heights(page), [0, 1]);
+
+        let page = chain.list_blocks(&BlockQuery {
+            from: Some(2),
+            limit: 2,
+            ..BlockQuery::default()
+        });
+        assert_eq!(page.next, Some(4));
+        assert_eq!(heights(page), [2, 3]);
+
+        let page = chain.list_blocks(&BlockQuery {
+            from: Some(4),
+            limit: 2,
+            ..BlockQuery::default()
+        });
+        assert_eq!(page.next, None);
+        assert_eq!(heights(page), [4]);
+
+        let page = chain.list_blocks(&BlockQuery {
+            order: Order::Desc,
+            limit: 3,
+            ..BlockQuery::default()
+        });
+        assert_eq!(page.next, Some(1));
+        assert_eq!(heights(page), [4, 3, 2]);
+
+        let page = chain.list_blocks(&BlockQuery {
+            miner: Some(coinbase.to_string()),
+            ..BlockQuery::default()
+        });
+        assert_eq!(page.next, None);
+        assert_eq!(heights(page), [2, 4]);
+
+        let page = chain.list_blocks(&BlockQuery {
+            until: Some(0),
+            ..BlockQuery::default()
+        });
+        assert!(page.blocks.is_empty());
+    }
+
+    #[test]
+    fn subscribe_to_events() {
+        let config = get_config();
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+        let events = chain.subscribe();
+        drop(chain.subscribe());
+
+        let mut tx = new_tx(0);
+        let signature = sign(&tx);
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap();
+        let hash = chain.mine_new_block().unwrap().hash();
+
+        // Rejected transactions are not published
+        chain
+            .add_transaction(&mut tx, &public_key(), &signature)
+            .unwrap_err();
+
+        let events: Vec<ChainEvent> = events.try_iter().collect();
+        assert_eq!(events.len(), 2);
+        assert!(
+            matches!(&events[0], ChainEvent::Transaction(added) if added.hash.to_string() == tx.hash.to_string())
+        );
+        assert!(matches!(&events[1], ChainEvent::Block(block) if block.hash() == hash));
+        assert_eq!(chain.subscribers.len(), 1);
+    }
+
     #[test]
     fn set_reward() {
         let config = get_config();
-        let mut chain = Chain::new(config, "test_miner");
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        chain.set_reward(amount("24.2")).unwrap();
+
+        assert_eq!(chain.reward(), amount("24.2"));
+
+        // Outside dev mode the reward follows the reward schedule
+        let mut config = get_config();
+        config.dev_mode = false;
+        config.halving_interval = 1;
+        let mut chain = Chain::new(config, &miner(), Box::new(MemoryStore::new()));
+
+        assert!(matches!(
+            chain.set_reward(amount("24.2")),
+            Err(ChainError::DevModeOnly)
+        ));
+        assert_eq!(chain.reward(), amount("12.1"));
+        let mut block = chain.block_template();
+        Chain::proof_of_work(&mut block);
+        chain.submit_block(block).unwrap();
+        assert_eq!(chain.reward(), amount("6.05"));
+    }
 
-        chain.set_reward(24.2);
+    /// Chain sharing the genesis block of `chain` but paying another miner, to
+    /// mine a competing branch on.
+    fn fork_of(chain: &Chain) -> Chain {
+        let mut store = MemoryStore::new();
+        store.append_block(chain.blocks()[0].clone()).unwrap();
+        let miner = Wallet::from_secret(&[9u8; 32]).address().clone();
+        Chain::new(get_config(), &miner, Box::new(store))
+    }
 
-        assert_eq!(chain.reward() as f64, 24.2);
+    /// Store that refuses the next block appended once `fail` is set.
+    struct FailingStore {
+        inner: MemoryStore,
+        fail: Arc<AtomicBool>,
+    }
+
+    impl BlockStore for FailingStore {
+        fn append_block(&mut self, block: Block) -> io::Result<()> {
+            if self.fail.swap(false, Ordering::SeqCst) {
+                return Err(io::Error::other("disk full"));
+            }
+            self.inner.append_block(block)
+        }
+
+        fn truncate(&mut self, height: usize) -> io::Result<()> {
+            self.inner.truncate(height)
+        }
+
+        fn get_block(&self, index: usize) -> Option<&Block> {
+            self.inner.get_block(index)
+        }
+
+        fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+            self.inner.get_block_by_hash(hash)
+        }
+
+        fn iter_range(&self, range: Range<usize>) -> Box<dyn Iterator<Item = &Block> + '_> {
+            self.inner.iter_range(range)
+        }
+
+        fn height(&self) -> usize {
+            self.inner.height()
+        }
+
+        fn load_pending(&self) -> io::Result<Vec<Transaction>> {
+            self.inner.load_pending()
+        }
+
+        fn save_pending(&mut self, txs: &[Transaction]) -> io::Result<()> {
+            self.inner.save_pending(txs)
+        }
+    }
+
+    fn mine_empty(chain: &mut Chain) -> Block {
+        let mut block = chain.block_template();
+        Chain::proof_of_work(&mut block);
+        chain.submit_block(block).unwrap().clone()
     }
 
     mod test_utils {
         use crate::blockchain::{
+            amount::Amount,
             config::ChainConfig,
             hasher::{Hash, Hasher},
+            mempool::MempoolConfig,
             models::TransactionData,
+            spec::ChainSpec,
             transaction::{Transaction, TransactionType},
             utils::timestamp,
         };
+        use crate::wallet::{Address, Wallet};
+
+        fn wallet() -> Wallet {
+            Wallet::from_secret(&[7u8; 32])
+        }
 
-        pub fn new_tx_data(amount: f64) -> TransactionData {
+        /// The test sender is also the miner, so it is funded by the genesis reward.
+        pub fn miner() -> Address {
+            wallet().address().clone()
+        }
+
+        pub fn sender() -> String {
+            wallet().address().to_string()
+        }
+
+        pub fn public_key() -> String {
+            wallet().public_key()
+        }
+
+        pub fn sign(tx: &Transaction) -> String {
+            wallet().sign(tx, "test")
+        }
+
+        pub fn amount(amount: &str) -> Amount {
+            amount.parse().unwrap()
+        }
+
+        pub fn new_tx_data(amount: &str, nonce: u64) -> TransactionData {
             TransactionData::TransferData {
-                sender: "me".to_string(),
+                sender: sender(),
                 receiver: "you".to_string(),
-                amount,
+                amount: self::amount(amount),
+                fee: Amount::ZERO,
+                nonce,
             }
         }
 
         pub fn get_config() -> ChainConfig {
-            ChainConfig {
-                difficulty: 0,
-                reward: 12.1,
-            }
+            let mut spec = ChainSpec::test(sender(), amount("12.1"));
+            spec.genesis.difficulty = 0;
+            spec.rewards.initial = amount("12.1");
+            spec.consensus.retarget_window = 0;
+            spec.config(MempoolConfig::default())
         }
 
-        pub fn new_tx() -> Transaction {
+        pub fn new_tx(nonce: u64) -> Transaction {
             let tx_data = TransactionData::TransferData {
-                sender: "me".to_string(),
+                sender: sender(),
                 receiver: "you".to_string(),
-                amount: 22.4,
+                amount: amount("2.4"),
+                fee: Amount::ZERO,
+                nonce,
             };
             let timestamp = timestamp();
             let mut hash_buf = Hash::new();
             let hash = Hasher::hash_tx_data(&tx_data, timestamp, &mut hash_buf);
-            Transaction::new(tx_data, TransactionType::Transfer, 1, hash.to_owned())
+            Transaction::new(
+                tx_data,
+                TransactionType::Transfer,
+                timestamp,
+                hash.to_owned(),
+            )
         }
     }
 }
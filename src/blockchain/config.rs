@@ -1,7 +1,84 @@
 use serde::{Deserialize, Serialize};
 
+use super::amount::Amount;
+use super::genesis::GenesisSpec;
+use super::mempool::MempoolConfig;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ChainConfig {
+    /// Starting difficulty, in leading zero hex digits of the block hash.
     pub difficulty: usize,
-    pub reward: f64,
+    /// Reward of the first block after genesis.
+    pub reward: Amount,
+    /// Number of blocks after which the reward halves, 0 keeps it fixed.
+    pub halving_interval: usize,
+    /// Seconds the chain aims to take per block.
+    pub target_block_time: u64,
+    /// Number of blocks between difficulty adjustments, 0 disables them.
+    pub retarget_window: usize,
+    /// Mine at the configured difficulty and reward without retargeting or
+    /// halving, allowing both to be changed at runtime. Not for use on shared
+    /// networks.
+    pub dev_mode: bool,
+    /// Start of every address on the network.
+    pub address_prefix: String,
+    /// Limits every block must stay within.
+    pub limits: BlockLimits,
+    /// Limits on the pool of pending transactions.
+    pub mempool: MempoolConfig,
+    /// Genesis block every node of the network starts from.
+    pub genesis: GenesisSpec,
+}
+
+impl ChainConfig {
+    /// Reward of the block at `index`, halved every `halving_interval` blocks.
+    pub fn reward_at(&self, index: usize) -> Amount {
+        if self.dev_mode || self.halving_interval == 0 {
+            return self.reward;
+        }
+        let halvings = (index.saturating_sub(1) / self.halving_interval) as u32;
+        let units = self.reward.base_units().checked_shr(halvings).unwrap_or(0);
+        Amount::from_base_units(units)
+    }
+}
+
+/// Size limits of a block, its reward included.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlockLimits {
+    /// Most transactions in a block.
+    pub max_block_txs: usize,
+    /// Most serialized bytes in a block.
+    pub max_block_bytes: usize,
+}
+
+impl Default for BlockLimits {
+    fn default() -> Self {
+        BlockLimits {
+            max_block_txs: 1_001,
+            max_block_bytes: 1024 * 1024,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::spec::ChainSpec;
+
+    #[test]
+    fn halve_reward() {
+        let mut spec = ChainSpec::test("genesis", Amount::ZERO);
+        spec.rewards.initial = Amount::from_coins(8).unwrap();
+        spec.rewards.halving_interval = 2;
+        spec.consensus.dev_mode = false;
+        let mut config = spec.config(MempoolConfig::default());
+
+        let rewards: Vec<Amount> = (1..=5).map(|index| config.reward_at(index)).collect();
+        let coins = |coins| Amount::from_coins(coins).unwrap();
+        assert_eq!(rewards, [coins(8), coins(8), coins(4), coins(4), coins(2)]);
+        assert_eq!(config.reward_at(2 * 64 + 1), Amount::ZERO);
+
+        config.halving_interval = 0;
+        assert_eq!(config.reward_at(1_000), coins(8));
+    }
 }
@@ -0,0 +1,135 @@
+use super::block::Block;
+use super::config::ChainConfig;
+use super::target::Target;
+
+/// Largest factor the target may move by in a single retarget.
+pub const MAX_ADJUSTMENT: u64 = 4;
+
+/// Target the block at `height` must be mined against, computed from its
+/// ancestors, which `block_at` looks up by index.
+///
+/// Genesis uses the configured starting difficulty. Every `retarget_window`
+/// blocks the parent target is scaled by how long the last window actually
+/// took compared to `target_block_time`, clamped to `MAX_ADJUSTMENT` in either
+/// direction. All other blocks keep their parent's target.
+pub fn required_target<'a>(
+    config: &ChainConfig,
+    height: usize,
+    block_at: impl Fn(usize) -> Option<&'a Block>,
+) -> Target {
+    let initial = Target::from_difficulty(config.difficulty);
+    let parent = match height.checked_sub(1).and_then(&block_at) {
+        Some(parent) => parent,
+        None => return initial,
+    };
+
+    // A window needs at least two blocks to measure an interval
+    let window = config.retarget_window;
+    if window < 2 || config.target_block_time == 0 || !height.is_multiple_of(window) {
+        return parent.header.target;
+    }
+    let first = match block_at(height - window) {
+        Some(first) => first,
+        None => return parent.header.target,
+    };
+
+    let expected = config.target_block_time.saturating_mul(window as u64 - 1);
+    let actual = parent
+        .header
+        .timestamp
+        .saturating_sub(first.header.timestamp)
+        .clamp(
+            expected / MAX_ADJUSTMENT,
+            expected.saturating_mul(MAX_ADJUSTMENT),
+        );
+
+    // Never easier than the starting difficulty
+    parent.header.target.scale(actual, expected).min(initial)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{amount::Amount, mempool::MempoolConfig, spec::ChainSpec};
+
+    fn config() -> ChainConfig {
+        let mut spec = ChainSpec::test("genesis", Amount::ZERO);
+        spec.genesis.difficulty = 1;
+        spec.rewards.initial = Amount::ZERO;
+        spec.consensus.retarget_window = 5;
+        spec.consensus.dev_mode = false;
+        spec.config(MempoolConfig::default())
+    }
+
+    /// Blocks mined `interval` seconds apart, all at `target`.
+    fn blocks(count: usize, interval: u64, target: Target) -> Vec<Block> {
+        (0..count)
+            .map(|index| {
+                let mut block =
+                    Block::new(index, 0, Vec::new(), "merkle", "prev", target, Amount::ZERO);
+                block.header.timestamp = 1_000 + index as u64 * interval;
+                block
+            })
+            .collect()
+    }
+
+    fn required(blocks: &[Block], height: usize) -> Target {
+        required_target(&config(), height, |index| blocks.get(index))
+    }
+
+    #[test]
+    fn genesis_and_between_windows() {
+        let target = Target::from_difficulty(3);
+        let blocks = blocks(7, 1, target);
+
+        assert_eq!(required(&blocks, 0), Target::from_difficulty(1));
+        for height in [1, 4, 6, 7] {
+            assert_eq!(required(&blocks, height), target);
+        }
+    }
+
+    #[test]
+    fn retarget_on_window_boundary() {
+        let target = Target::from_difficulty(3);
+
+        // On schedule, 4 intervals of 10 seconds
+        let blocks = self::blocks(5, 10, target);
+        assert_eq!(required(&blocks, 5), target);
+
+        // Twice as fast halves the target
+        let blocks = self::blocks(5, 5, target);
+        assert_eq!(required(&blocks, 5), target.scale(1, 2));
+
+        // Twice as slow doubles it
+        let blocks = self::blocks(5, 20, target);
+        assert_eq!(required(&blocks, 5), target.scale(2, 1));
+    }
+
+    #[test]
+    fn adjustment_is_clamped() {
+        let target = Target::from_difficulty(3);
+
+        let blocks = self::blocks(5, 0, target);
+        assert_eq!(required(&blocks, 5), target.scale(1, MAX_ADJUSTMENT));
+
+        let blocks = self::blocks(5, 1_000, target);
+        assert_eq!(required(&blocks, 5), target.scale(MAX_ADJUSTMENT, 1));
+
+        // Never easier than the starting difficulty
+        let blocks = self::blocks(5, 1_000, Target::from_difficulty(1));
+        assert_eq!(required(&blocks, 5), Target::from_difficulty(1));
+    }
+
+    #[test]
+    fn retargeting_disabled() {
+        let mut config = config();
+        config.retarget_window = 0;
+        let target = Target::from_difficulty(3);
+        let blocks = blocks(5, 0, target);
+
+        assert_eq!(
+            required_target(&config, 5, |index| blocks.get(index)),
+            target
+        );
+    }
+}
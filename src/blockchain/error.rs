@@ -0,0 +1,100 @@
+use std::{error::Error, fmt, io};
+
+use super::amount::{Amount, AmountError};
+use super::mempool::MempoolError;
+use super::state::StateError;
+use super::transaction::VerifyError;
+use super::validation::BlockError;
+
+#[derive(Debug)]
+pub enum ChainError {
+    TransactionNotVerified(VerifyError),
+    TransactionHashMismatch,
+    InvalidTransactionType,
+    InvalidAmount,
+    InsufficientFunds { available: Amount, required: Amount },
+    FeeTooLow { fee: Amount, minimum: Amount },
+    InvalidNonce { expected: u64, found: u64 },
+    DuplicateTransaction,
+    Amount(AmountError),
+    Mempool(MempoolError),
+    DevModeOnly,
+    InvalidBlock(BlockError),
+    StaleBlock,
+    KnownBlock,
+    UnknownParent,
+    Storage(io::Error),
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TransactionNotVerified(err) => write!(f, "Transaction not verified: {err}"),
+            Self::TransactionHashMismatch => write!(f, "Transaction does not match its hash"),
+            Self::InvalidTransactionType => write!(f, "Only transfers can be submitted"),
+            Self::InvalidAmount => write!(f, "Amount must be greater than zero"),
+            Self::InsufficientFunds {
+                available,
+                required,
+            } => write!(
+                f,
+                "Insufficient funds: {available} available, {required} required"
+            ),
+            Self::FeeTooLow { fee, minimum } => {
+                write!(f, "Fee of {fee} is below the minimum of {minimum}")
+            }
+            Self::InvalidNonce { expected, found } => {
+                write!(f, "Nonce is {found}, expected {expected}")
+            }
+            Self::DuplicateTransaction => write!(f, "Transaction is already pending or mined"),
+            Self::Amount(err) => write!(f, "Invalid amount: {err}"),
+            Self::Mempool(err) => write!(f, "Transaction not accepted: {err}"),
+            Self::DevModeOnly => write!(f, "Only available in dev mode"),
+            Self::InvalidBlock(err) => write!(f, "Invalid block: {err}"),
+            Self::StaleBlock => write!(f, "Block does not extend the best chain"),
+            Self::KnownBlock => write!(f, "Block is already known"),
+            Self::UnknownParent => write!(f, "Block builds on an unknown block"),
+            Self::Storage(err) => write!(f, "Storage error: {err}"),
+        }
+    }
+}
+
+impl Error for ChainError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::TransactionNotVerified(err) => Some(err),
+            Self::Amount(err) => Some(err),
+            Self::Mempool(err) => Some(err),
+            Self::InvalidBlock(err) => Some(err),
+            Self::Storage(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<AmountError> for ChainError {
+    fn from(err: AmountError) -> Self {
+        Self::Amount(err)
+    }
+}
+
+impl From<MempoolError> for ChainError {
+    fn from(err: MempoolError) -> Self {
+        Self::Mempool(err)
+    }
+}
+
+impl From<StateError> for ChainError {
+    fn from(err: StateError) -> Self {
+        match err {
+            StateError::Amount(err) => Self::Amount(err),
+            StateError::NonceMismatch { expected, found } => Self::InvalidNonce { expected, found },
+        }
+    }
+}
+
+impl From<io::Error> for ChainError {
+    fn from(err: io::Error) -> Self {
+        Self::Storage(err)
+    }
+}
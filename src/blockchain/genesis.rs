@@ -0,0 +1,195 @@
+use std::fmt;
+
+use hex_fmt::HexFmt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::amount::Amount;
+use super::block::Block;
+use super::chain::Chain;
+use super::hasher::Hasher;
+use super::models::TransactionData;
+use super::target::Target;
+use super::transaction::TransactionType;
+
+/// Coins paid to `address` by the genesis block.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Allocation {
+    pub address: String,
+    pub amount: Amount,
+}
+
+/// Everything the genesis block is built from. Nodes given the same spec
+/// build the very same genesis block, and so can agree on a chain.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenesisSpec {
+    /// Name of the network. The genesis block commits to it, so separate
+    /// networks never share a genesis block.
+    pub chain_id: String,
+    /// Timestamp of the genesis block and its transactions.
+    pub timestamp: u64,
+    /// Starting difficulty, in leading zero hex digits of the block hash.
+    pub difficulty: usize,
+    /// Coins paid out by the genesis block, one transaction each.
+    pub allocations: Vec<Allocation>,
+    /// Free form data the genesis block commits to.
+    #[serde(default)]
+    pub extra_data: String,
+    /// Hash the genesis block must have, pinning the spec to a known block.
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum GenesisError {
+    NoAllocations,
+    HashMismatch { expected: String, found: String },
+}
+
+impl fmt::Display for GenesisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAllocations => write!(f, "Genesis spec allocates no coins"),
+            Self::HashMismatch { expected, found } => {
+                write!(f, "Genesis block hash is {found}, spec pins {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GenesisError {}
+
+impl GenesisSpec {
+    /// Build the genesis block, making sure it pays out coins and has the
+    /// pinned hash if there is one.
+    pub fn check(&self) -> Result<Block, GenesisError> {
+        if self.allocations.is_empty() {
+            return Err(GenesisError::NoAllocations);
+        }
+
+        let block = self.block();
+        match &self.hash {
+            Some(expected) if *expected != block.hash() => Err(GenesisError::HashMismatch {
+                expected: expected.clone(),
+                found: block.hash(),
+            }),
+            _ => Ok(block),
+        }
+    }
+
+    /// Genesis block described by the spec. Its proof of work is searched from
+    /// nonce zero, so the block only depends on the spec.
+    pub fn block(&self) -> Block {
+        // Numbered so that equal allocations still get distinct hashes
+        let txs = self
+            .allocations
+            .iter()
+            .enumerate()
+            .map(|(nonce, allocation)| {
+                let data = TransactionData::TransferData {
+                    sender: "Root".to_string(),
+                    receiver: allocation.address.clone(),
+                    amount: allocation.amount,
+                    fee: Amount::ZERO,
+                    nonce: nonce as u64,
+                };
+                Chain::new_transaction_at(data, TransactionType::GenesisReward, self.timestamp)
+            })
+            .collect();
+        let txs = Chain::confirm_transactions(txs);
+        let merkle_root = Hasher::merkle_root(&txs);
+
+        let mut block = Block::new(
+            0,
+            0,
+            txs,
+            &merkle_root,
+            &self.previous_hash(),
+            Target::from_difficulty(self.difficulty),
+            self.total(),
+        );
+        block.header.timestamp = self.timestamp;
+        while !block.header.meets_target() {
+            block.header.nonce += 1;
+        }
+        block
+    }
+
+    /// Stands in for the parent hash of the genesis block, committing it to
+    /// the chain id and extra data.
+    pub fn previous_hash(&self) -> String {
+        let bytes = bincode::serialize(&(&self.chain_id, &self.extra_data)).unwrap();
+        format!("{}", HexFmt(Sha256::digest(bytes)))
+    }
+
+    /// Coins paid out by the genesis block, recorded as its reward.
+    pub fn total(&self) -> Amount {
+        self.allocations
+            .iter()
+            .map(|allocation| allocation.amount)
+            .fold(Amount::ZERO, Amount::saturating_add)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spec() -> GenesisSpec {
+        GenesisSpec {
+            chain_id: "testnet".to_string(),
+            timestamp: 1_700_000_000,
+            difficulty: 1,
+            allocations: vec![
+                Allocation {
+                    address: "alice".to_string(),
+                    amount: Amount::from_coins(10).unwrap(),
+                },
+                Allocation {
+                    address: "bob".to_string(),
+                    amount: Amount::from_coins(5).unwrap(),
+                },
+            ],
+            extra_data: "first".to_string(),
+            hash: None,
+        }
+    }
+
+    #[test]
+    fn same_spec_same_block() {
+        let block = spec().block();
+        assert_eq!(block.hash(), spec().block().hash());
+        assert_eq!(block.header.timestamp, 1_700_000_000);
+        assert_eq!(block.header.reward, Amount::from_coins(15).unwrap());
+        assert_eq!(block.miner(), Some("bob"));
+        assert!(block.header.meets_target());
+
+        // Every field of the spec goes into the block
+        let mut other = spec();
+        other.chain_id = "mainnet".to_string();
+        assert_ne!(other.block().hash(), block.hash());
+        let mut other = spec();
+        other.extra_data.clear();
+        assert_ne!(other.block().hash(), block.hash());
+        let mut other = spec();
+        other.allocations[1].amount = Amount::from_coins(6).unwrap();
+        assert_ne!(other.block().hash(), block.hash());
+    }
+
+    #[test]
+    fn pinned_hash() {
+        let mut spec = spec();
+        let hash = spec.block().hash();
+        spec.hash = Some(hash.clone());
+        assert_eq!(spec.check().unwrap().hash(), hash);
+
+        spec.timestamp += 1;
+        assert!(matches!(
+            spec.check(),
+            Err(GenesisError::HashMismatch { expected, .. }) if expected == hash
+        ));
+
+        spec.allocations.clear();
+        assert!(matches!(spec.check(), Err(GenesisError::NoAllocations)));
+    }
+}
@@ -1,10 +1,10 @@
 use std::fmt;
 
 use hex_fmt::HexFmt;
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use sha2::{Digest, Sha256};
 
-use super::{models::TransactionData, transaction::Transaction};
+use super::{merkle::MerkleTree, models::TransactionData, transaction::Transaction};
 
 #[derive(Clone, Debug)]
 pub struct Hash {
@@ -32,6 +32,22 @@ impl Serialize for Hash {
     }
 }
 
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        if string.len() > 64 {
+            return Err(de::Error::invalid_length(string.len(), &"at most 64 bytes"));
+        }
+
+        let mut hash = Hash::new();
+        hash.write(&string);
+        Ok(hash)
+    }
+}
+
 impl Default for Hash {
     fn default() -> Self {
         Self::new()
@@ -87,30 +103,7 @@ impl Hasher {
     }
 
     pub fn merkle_root(txs: &[Transaction]) -> String {
-        match txs.len() {
-            1 => Hasher::hash_two_txs(&txs[0], &txs[0]),
-            2 => Hasher::hash_two_txs(&txs[0], &txs[1]),
-            _ => Hasher::merkle_root(&txs[..txs.len() - 2]),
-        }
-    }
-
-    // ---
-    // Private methods
-    // ---
-    fn hash_two_txs(tx1: &Transaction, tx2: &Transaction) -> String {
-        let data = format!("{}{}", tx1.hash, tx2.hash);
-
-        let bytes = bincode::serialize(&data).unwrap();
-        // create a Sha256 object
-        let mut hasher = Sha256::new();
-
-        // write input message
-        hasher.update(bytes);
-
-        // read hash digest and consume hasher
-        let result = hasher.finalize();
-
-        format!("{}", HexFmt(result))
+        MerkleTree::from_transactions(txs).root()
     }
 }
 
@@ -149,8 +142,8 @@ mod test {
         let mut tx_vec_1: Vec<Transaction> = Vec::new();
         let mut tx_vec_2: Vec<Transaction> = Vec::new();
 
-        let tx = new_tx(10.1, 1);
-        let tx2 = new_tx(10.12, 2);
+        let tx = new_tx("10.1", 1);
+        let tx2 = new_tx("10.12", 2);
 
         for _ in 0..5 {
             tx_vec_1.push(tx.clone());
@@ -163,9 +156,15 @@ mod test {
         assert_ne!(merkle_1, merkle_2);
 
         let merkle_3 = Hasher::merkle_root(&[tx.clone(), tx.clone()]);
-        let merkle_4 = Hasher::merkle_root(&[tx.clone()]);
+        let merkle_4 = Hasher::merkle_root(std::slice::from_ref(&tx));
+
+        assert_ne!(merkle_3, merkle_4);
+
+        // Every transaction is covered, not just the first ones
+        let mut tx_vec_3 = tx_vec_1.clone();
+        tx_vec_3[4] = tx2.clone();
 
-        assert_eq!(merkle_3, merkle_4);
+        assert_ne!(merkle_1, Hasher::merkle_root(&tx_vec_3));
 
         let merkle_5 = Hasher::merkle_root(&[tx]);
         let merkle_6 = Hasher::merkle_root(&[tx2]);
@@ -176,16 +175,19 @@ mod test {
     mod test_utils {
         use super::*;
         use crate::blockchain::{
+            amount::Amount,
             hasher::{Hash, Hasher},
             models::TransactionData,
             transaction::TransactionType,
         };
 
-        pub fn new_tx(amount: f64, timestamp: u64) -> Transaction {
+        pub fn new_tx(amount: &str, timestamp: u64) -> Transaction {
             let tx_data = TransactionData::TransferData {
                 sender: "me".to_string(),
                 receiver: "you".to_string(),
-                amount,
+                amount: amount.parse().unwrap(),
+                fee: Amount::ZERO,
+                nonce: 0,
             };
             let mut hash_buf = Hash::new();
             let hash = Hasher::hash_tx_data(&tx_data, timestamp, &mut hash_buf);
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::block::Block;
+use super::transaction::TransactionType;
+
+/// Where a confirmed transaction sits in the chain.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct TxLocation {
+    pub block_index: usize,
+    /// Position of the transaction within its block.
+    pub position: usize,
+}
+
+/// Lookups over confirmed transactions by hash and by address, kept up to date
+/// as blocks are added. Blocks themselves are looked up by hash in the store.
+#[derive(Default)]
+pub struct ChainIndex {
+    txs: HashMap<String, TxLocation>,
+    // Hashes of the transactions sending to or from each address, oldest first
+    addresses: HashMap<String, Vec<String>>,
+}
+
+impl ChainIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_blocks<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> Self {
+        let mut index = ChainIndex::new();
+        for block in blocks {
+            index.add_block(block);
+        }
+        index
+    }
+
+    /// Index every transaction of `block`, which must be the next block of the
+    /// chain.
+    pub fn add_block(&mut self, block: &Block) {
+        for (position, tx) in block.txs.iter().enumerate() {
+            let tx_hash = tx.hash.to_string();
+            let location = TxLocation {
+                block_index: block.header.index,
+                position,
+            };
+            self.txs.insert(tx_hash.clone(), location);
+
+            // Rewards are minted rather than sent from an account
+            let sender = (tx.tx_type == TransactionType::Transfer).then(|| tx.tx_data.sender());
+            let receiver = tx
+                .tx_data
+                .receiver()
+                .filter(|receiver| Some(*receiver) != sender);
+            for address in sender.into_iter().chain(receiver) {
+                self.addresses
+                    .entry(address.to_string())
+                    .or_default()
+                    .push(tx_hash.clone());
+            }
+        }
+    }
+
+    pub fn locate(&self, tx_hash: &str) -> Option<TxLocation> {
+        self.txs.get(tx_hash).copied()
+    }
+
+    pub fn contains(&self, tx_hash: &str) -> bool {
+        self.txs.contains_key(tx_hash)
+    }
+
+    /// Hashes of the confirmed transactions sending to or from `address`, in
+    /// chain order.
+    pub fn transactions_of(&self, address: &str) -> &[String] {
+        self.addresses.get(address).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{
+        amount::Amount, chain::Chain, models::TransactionData, target::Target,
+        transaction::Transaction,
+    };
+
+    fn transfer(sender: &str, receiver: &str, tx_type: TransactionType) -> Transaction {
+        let tx_data = TransactionData::TransferData {
+            sender: sender.to_string(),
+            receiver: receiver.to_string(),
+            amount: Amount::from_coins(1).unwrap(),
+            fee: Amount::ZERO,
+            nonce: 0,
+        };
+        Chain::new_transaction(tx_data, tx_type)
+    }
+
+    fn block(index: usize, txs: Vec<Transaction>) -> Block {
+        Block::new(
+            index,
+            0,
+            txs,
+            "merkle",
+            "prev_hash",
+            Target::MAX,
+            Amount::ZERO,
+        )
+    }
+
+    #[test]
+    fn index_blocks() {
+        let reward = transfer("Root", "alice", TransactionType::GenesisReward);
+        let sent = transfer("alice", "bob", TransactionType::Transfer);
+        let blocks = vec![
+            block(0, vec![reward.clone()]),
+            block(
+                1,
+                vec![
+                    transfer("Root", "carol", TransactionType::Reward),
+                    sent.clone(),
+                ],
+            ),
+        ];
+
+        let index = ChainIndex::from_blocks(&blocks);
+
+        assert_eq!(
+            index.locate(&sent.hash.to_string()),
+            Some(TxLocation {
+                block_index: 1,
+                position: 1
+            })
+        );
+        assert!(index.contains(&reward.hash.to_string()));
+        assert!(!index.contains("unknown"));
+
+        assert_eq!(
+            index.transactions_of("alice"),
+            [reward.hash.to_string(), sent.hash.to_string()]
+        );
+        assert_eq!(index.transactions_of("bob"), [sent.hash.to_string()]);
+        assert!(index.transactions_of("Root").is_empty());
+        assert!(index.transactions_of("dave").is_empty());
+    }
+}
@@ -0,0 +1,499 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::amount::Amount;
+use super::transaction::Transaction;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MempoolConfig {
+    /// Most transactions held at a time.
+    pub max_count: usize,
+    /// Most serialized bytes of transactions held at a time.
+    pub max_bytes: usize,
+    /// Most transactions held at a time from a single sender.
+    pub max_per_sender: usize,
+    /// Seconds a transaction may wait to be mined before it is dropped.
+    pub max_age: u64,
+    /// Lowest fee a transfer has to pay to be accepted.
+    pub min_relay_fee: Amount,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        MempoolConfig {
+            max_count: 5_000,
+            max_bytes: 5 * 1024 * 1024,
+            max_per_sender: 100,
+            max_age: 3 * 60 * 60,
+            min_relay_fee: Amount::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MempoolError {
+    /// The transaction alone is over the byte limit of the pool.
+    TooLarge { size: usize, max: usize },
+    /// The sender already has the most transactions allowed waiting.
+    SenderLimit { max: usize },
+    /// The pool is full of transactions paying at least as much.
+    Full,
+}
+
+impl fmt::Display for MempoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge { size, max } => {
+                write!(
+                    f,
+                    "transaction of {size} bytes is over the {max} byte limit"
+                )
+            }
+            Self::SenderLimit { max } => {
+                write!(f, "sender already has {max} pending transactions")
+            }
+            Self::Full => write!(f, "pool is full, a higher fee is required"),
+        }
+    }
+}
+
+impl std::error::Error for MempoolError {}
+
+/// Pending transactions waiting to be mined, best paying first.
+///
+/// Transactions are ranked by fee per serialized byte, ties going to the one
+/// that arrived first. Once a limit is reached a new transaction only gets in
+/// by outranking, and evicting, the lowest ranked ones.
+///
+/// A sender's transactions can only be mined in nonce order, so whenever one
+/// is evicted or expires the sender's later transactions go with it.
+pub struct Mempool {
+    config: MempoolConfig,
+    // Kept sorted by rank, best first
+    entries: Vec<Entry>,
+    hashes: HashSet<String>,
+    bytes: usize,
+    next_sequence: u64,
+}
+
+struct Entry {
+    tx: Transaction,
+    fee: Amount,
+    nonce: u64,
+    size: usize,
+    sequence: u64,
+    added_at: u64,
+}
+
+impl Entry {
+    /// `Less` when `self` is mined before `other`.
+    fn rank(&self, other: &Entry) -> Ordering {
+        // Compare fee / size without dividing
+        let fee_rate = self.fee.base_units() as u128 * other.size as u128;
+        let other_fee_rate = other.fee.base_units() as u128 * self.size as u128;
+
+        other_fee_rate
+            .cmp(&fee_rate)
+            .then(self.sequence.cmp(&other.sequence))
+    }
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Mempool {
+            config,
+            entries: Vec::new(),
+            hashes: HashSet::new(),
+            bytes: 0,
+            next_sequence: 0,
+        }
+    }
+
+    /// Add `tx` paying `fee`, received at `now`, evicting lower ranked
+    /// transactions if the pool is full.
+    pub fn insert(&mut self, tx: Transaction, fee: Amount, now: u64) -> Result<(), MempoolError> {
+        let size = bincode::serialized_size(&tx).unwrap() as usize;
+        if size > self.config.max_bytes {
+            return Err(MempoolError::TooLarge {
+                size,
+                max: self.config.max_bytes,
+            });
+        }
+
+        let sender = tx.tx_data.sender();
+        let from_sender = self
+            .entries
+            .iter()
+            .filter(|entry| entry.tx.tx_data.sender() == sender)
+            .count();
+        if from_sender >= self.config.max_per_sender {
+            return Err(MempoolError::SenderLimit {
+                max: self.config.max_per_sender,
+            });
+        }
+
+        let entry = Entry {
+            nonce: tx.tx_data.nonce().unwrap_or_default(),
+            tx,
+            fee,
+            size,
+            sequence: self.next_sequence,
+            added_at: now,
+        };
+
+        // Work out what has to go before evicting anything
+        let (mut count, mut bytes) = (self.entries.len() + 1, self.bytes + size);
+        let mut keep = self.entries.len();
+        while count > self.config.max_count || bytes > self.config.max_bytes {
+            let lowest = match keep.checked_sub(1) {
+                Some(index) => &self.entries[index],
+                None => return Err(MempoolError::Full),
+            };
+            // Evicting an earlier transaction of the sender would strand it
+            let same_sender = lowest.tx.tx_data.sender() == entry.tx.tx_data.sender();
+            if entry.rank(lowest) != Ordering::Less || same_sender {
+                return Err(MempoolError::Full);
+            }
+            keep -= 1;
+            count -= 1;
+            bytes -= lowest.size;
+        }
+        let evicted: HashSet<u64> = self.entries[keep..]
+            .iter()
+            .map(|entry| entry.sequence)
+            .collect();
+        self.drop_with_dependents(|entry| evicted.contains(&entry.sequence));
+
+        let position = self
+            .entries
+            .partition_point(|other| other.rank(&entry) == Ordering::Less);
+        self.bytes += entry.size;
+        self.hashes.insert(entry.tx.hash.to_string());
+        self.entries.insert(position, entry);
+        self.next_sequence += 1;
+        Ok(())
+    }
+
+    /// Remove every transaction whose hash is in `hashes`, e.g. once mined.
+    pub fn remove(&mut self, hashes: &HashSet<String>) {
+        self.retain(|entry| !hashes.contains(&entry.tx.hash.to_string()));
+    }
+
+    /// Remove transactions whose nonce `next_nonce` says has already been
+    /// used by their sender, e.g. by a conflicting transaction in a block.
+    pub fn prune(&mut self, next_nonce: impl Fn(&str) -> u64) {
+        self.retain(|entry| entry.nonce >= next_nonce(entry.tx.tx_data.sender()));
+    }
+
+    /// Drop transactions that have waited longer than the configured maximum
+    /// age at `now`, returning how many were dropped.
+    pub fn expire(&mut self, now: u64) -> usize {
+        let max_age = self.config.max_age;
+        let before = self.entries.len();
+        self.drop_with_dependents(|entry| now.saturating_sub(entry.added_at) > max_age);
+        before - self.entries.len()
+    }
+
+    /// Highest ranked transactions that can be mined in order on top of the
+    /// confirmed `next_nonce` of each sender, at most `max` of them. A
+    /// transaction outranking the one before it from the same sender waits for
+    /// it to be taken first.
+    pub fn select(&self, max: usize, next_nonce: impl Fn(&str) -> u64) -> Vec<&Transaction> {
+        let mut nonces: HashMap<&str, u64> = HashMap::new();
+        let mut selected = vec![false; self.entries.len()];
+        let mut txs = Vec::new();
+
+        // Each pass takes every transaction whose turn has come, until none do
+        let mut progress = true;
+        while progress && txs.len() < max {
+            progress = false;
+            for (index, entry) in self.entries.iter().enumerate() {
+                if selected[index] || txs.len() >= max {
+                    continue;
+                }
+
+                let sender = entry.tx.tx_data.sender();
+                let nonce = nonces.entry(sender).or_insert_with(|| next_nonce(sender));
+                if entry.nonce == *nonce {
+                    *nonce += 1;
+                    selected[index] = true;
+                    txs.push(&entry.tx);
+                    progress = true;
+                }
+            }
+        }
+        txs
+    }
+
+    /// Nonce following the last pending transaction from `sender`, if any.
+    pub fn next_nonce(&self, sender: &str) -> Option<u64> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.tx.tx_data.sender() == sender)
+            .map(|entry| entry.nonce + 1)
+            .max()
+    }
+
+    /// Transactions in the order they would be mined.
+    pub fn iter(&self) -> impl Iterator<Item = &Transaction> {
+        self.entries.iter().map(|entry| &entry.tx)
+    }
+
+    /// Transactions in the order they arrived, the order to persist them in
+    /// so ties keep their rank once reloaded.
+    pub fn by_arrival(&self) -> Vec<Transaction> {
+        let mut entries: Vec<&Entry> = self.entries.iter().collect();
+        entries.sort_by_key(|entry| entry.sequence);
+        entries.into_iter().map(|entry| entry.tx.clone()).collect()
+    }
+
+    pub fn get(&self, tx_hash: &str) -> Option<&Transaction> {
+        if !self.contains(tx_hash) {
+            return None;
+        }
+        self.iter().find(|tx| tx.hash.to_string() == tx_hash)
+    }
+
+    pub fn contains(&self, tx_hash: &str) -> bool {
+        self.hashes.contains(tx_hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Serialized size of all transactions held.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // ---
+    // Private methods
+    // ---
+
+    fn retain(&mut self, keep: impl Fn(&Entry) -> bool) {
+        self.entries.retain(keep);
+        self.bytes = self.entries.iter().map(|entry| entry.size).sum();
+        self.hashes = self
+            .entries
+            .iter()
+            .map(|entry| entry.tx.hash.to_string())
+            .collect();
+    }
+
+    /// Drop the transactions matching `drop` along with every later
+    /// transaction from the same senders, which could no longer be mined.
+    fn drop_with_dependents(&mut self, drop: impl Fn(&Entry) -> bool) {
+        let mut first_dropped: HashMap<String, u64> = HashMap::new();
+        for entry in self.entries.iter().filter(|entry| drop(entry)) {
+            let first = first_dropped
+                .entry(entry.tx.tx_data.sender().to_string())
+                .or_insert(entry.nonce);
+            *first = (*first).min(entry.nonce);
+        }
+
+        self.retain(|entry| {
+            first_dropped
+                .get(entry.tx.tx_data.sender())
+                .is_none_or(|first| entry.nonce < *first)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{chain::Chain, models::TransactionData, transaction::TransactionType};
+
+    fn config() -> MempoolConfig {
+        MempoolConfig {
+            max_count: 3,
+            max_bytes: 10_000,
+            max_per_sender: 2,
+            max_age: 60,
+            min_relay_fee: Amount::ZERO,
+        }
+    }
+
+    fn tx(sender: &str, nonce: u64) -> Transaction {
+        let tx_data = TransactionData::TransferData {
+            sender: sender.to_string(),
+            receiver: "receiver".to_string(),
+            amount: Amount::from_base_units(1),
+            fee: Amount::ZERO,
+            nonce,
+        };
+        Chain::new_transaction_at(tx_data, TransactionType::Transfer, 1)
+    }
+
+    fn fee(units: u64) -> Amount {
+        Amount::from_base_units(units)
+    }
+
+    fn hashes<'a>(txs: impl Iterator<Item = &'a Transaction>) -> Vec<String> {
+        txs.map(|tx| tx.hash.to_string()).collect()
+    }
+
+    #[test]
+    fn order_by_fee_then_arrival() {
+        let mut mempool = Mempool::new(config());
+        let (first, second, third) = (tx("alice", 0), tx("bobby", 0), tx("carol", 0));
+
+        mempool.insert(first.clone(), fee(10), 0).unwrap();
+        mempool.insert(second.clone(), fee(10), 0).unwrap();
+        mempool.insert(third.clone(), fee(1_000), 0).unwrap();
+
+        assert_eq!(
+            hashes(mempool.iter()),
+            hashes([&third, &first, &second].into_iter())
+        );
+        assert_eq!(
+            hashes(mempool.by_arrival().iter()),
+            hashes([&first, &second, &third].into_iter())
+        );
+
+        // Capped at the most transactions asked for
+        assert_eq!(
+            hashes(mempool.select(2, |_| 0).into_iter()),
+            hashes([&third, &first].into_iter())
+        );
+    }
+
+    #[test]
+    fn evict_lowest_fee() {
+        let mut mempool = Mempool::new(config());
+        let low = tx("alice", 1);
+        mempool.insert(tx("bobby", 2), fee(100), 0).unwrap();
+        mempool.insert(low.clone(), fee(10), 0).unwrap();
+        mempool.insert(tx("carol", 3), fee(100), 0).unwrap();
+
+        // Not paying more than the lowest entry
+        assert_eq!(
+            mempool.insert(tx("david", 4), fee(10), 0),
+            Err(MempoolError::Full)
+        );
+
+        mempool.insert(tx("david", 4), fee(50), 0).unwrap();
+        assert_eq!(mempool.len(), 3);
+        assert!(mempool.get(&low.hash.to_string()).is_none());
+    }
+
+    #[test]
+    fn select_in_nonce_order() {
+        let mut mempool = Mempool::new(config());
+        let (first, second, other) = (tx("alice", 0), tx("alice", 1), tx("bobby", 0));
+        mempool.insert(first.clone(), fee(1), 0).unwrap();
+        mempool.insert(second.clone(), fee(100), 0).unwrap();
+        mempool.insert(other.clone(), fee(50), 0).unwrap();
+
+        // The second transfer outranks the first but has to follow it
+        assert_eq!(
+            hashes(mempool.select(3, |_| 0).into_iter()),
+            hashes([&other, &first, &second].into_iter())
+        );
+        assert_eq!(mempool.next_nonce("alice"), Some(2));
+        assert_eq!(mempool.next_nonce("carol"), None);
+
+        // Once the first is confirmed elsewhere it is stale
+        let next_nonce = |sender: &str| u64::from(sender == "alice");
+        assert_eq!(
+            hashes(mempool.select(3, next_nonce).into_iter()),
+            hashes([&second, &other].into_iter())
+        );
+        mempool.prune(next_nonce);
+        assert!(mempool.get(&first.hash.to_string()).is_none());
+        assert_eq!(mempool.len(), 2);
+    }
+
+    #[test]
+    fn drop_dependents() {
+        let mut mempool = Mempool::new(MempoolConfig {
+            max_per_sender: 3,
+            ..config()
+        });
+        mempool.insert(tx("alice", 0), fee(1), 0).unwrap();
+        mempool.insert(tx("alice", 1), fee(100), 0).unwrap();
+        mempool.insert(tx("bobby", 0), fee(100), 0).unwrap();
+
+        // Evicting the first would strand the new transfer
+        assert_eq!(
+            mempool.insert(tx("alice", 2), fee(1_000), 0),
+            Err(MempoolError::Full)
+        );
+
+        // The second transfer cannot be mined once the first is evicted
+        mempool.insert(tx("carol", 0), fee(50), 0).unwrap();
+        assert_eq!(mempool.len(), 2);
+        assert_eq!(mempool.next_nonce("alice"), None);
+
+        let mut mempool = Mempool::new(config());
+        mempool.insert(tx("alice", 0), fee(1), 0).unwrap();
+        mempool.insert(tx("alice", 1), fee(1), 30).unwrap();
+        assert_eq!(mempool.expire(61), 2);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn byte_limit() {
+        let size = bincode::serialized_size(&tx("alice", 1)).unwrap() as usize;
+        let mut mempool = Mempool::new(MempoolConfig {
+            max_bytes: size * 2,
+            ..config()
+        });
+
+        mempool.insert(tx("alice", 1), fee(1), 0).unwrap();
+        mempool.insert(tx("bobby", 1), fee(1), 0).unwrap();
+        assert_eq!(mempool.bytes(), size * 2);
+        assert_eq!(
+            mempool.insert(tx("carol", 1), fee(1), 0),
+            Err(MempoolError::Full)
+        );
+
+        let mut mempool = Mempool::new(MempoolConfig {
+            max_bytes: size - 1,
+            ..config()
+        });
+        assert_eq!(
+            mempool.insert(tx("alice", 1), fee(1), 0),
+            Err(MempoolError::TooLarge {
+                size,
+                max: size - 1
+            })
+        );
+    }
+
+    #[test]
+    fn sender_limit() {
+        let mut mempool = Mempool::new(config());
+        mempool.insert(tx("alice", 1), fee(1), 0).unwrap();
+        mempool.insert(tx("alice", 2), fee(1), 0).unwrap();
+
+        assert_eq!(
+            mempool.insert(tx("alice", 3), fee(1), 0),
+            Err(MempoolError::SenderLimit { max: 2 })
+        );
+    }
+
+    #[test]
+    fn expire_and_remove() {
+        let mut mempool = Mempool::new(config());
+        let (old, new) = (tx("alice", 1), tx("bobby", 1));
+        mempool.insert(old, fee(1), 0).unwrap();
+        mempool.insert(new.clone(), fee(1), 30).unwrap();
+
+        assert_eq!(mempool.expire(60), 0);
+        assert_eq!(mempool.expire(61), 1);
+        assert_eq!(hashes(mempool.iter()), hashes([&new].into_iter()));
+
+        mempool.remove(&HashSet::from([new.hash.to_string()]));
+        assert!(mempool.is_empty());
+        assert_eq!(mempool.bytes(), 0);
+    }
+}
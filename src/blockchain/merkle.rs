@@ -0,0 +1,234 @@
+use hex_fmt::HexFmt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::transaction::Transaction;
+
+// Domain separation so a leaf can never be passed off as an internal node
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+type Digest32 = [u8; 32];
+
+/// Binary merkle tree over transaction hashes.
+///
+/// Levels are hashed pairwise from the leaves up. A node without a sibling is
+/// promoted to the next level unchanged rather than paired with itself, so no
+/// two different lists of transactions share a root.
+pub struct MerkleTree {
+    levels: Vec<Vec<Digest32>>,
+}
+
+impl MerkleTree {
+    pub fn from_transactions(txs: &[Transaction]) -> Self {
+        MerkleTree::new(txs.iter().map(|tx| tx.hash.to_string()))
+    }
+
+    pub fn new<T: AsRef<[u8]>>(leaves: impl IntoIterator<Item = T>) -> Self {
+        let mut levels = vec![leaves
+            .into_iter()
+            .map(|leaf| MerkleTree::hash_leaf(leaf.as_ref()))
+            .collect::<Vec<_>>()];
+
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => MerkleTree::hash_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        MerkleTree { levels }
+    }
+
+    /// Hex encoded root, all zeros for an empty tree.
+    pub fn root(&self) -> String {
+        let root = self.levels.last().unwrap().first().copied();
+        format!("{}", HexFmt(root.unwrap_or_default()))
+    }
+
+    /// Proof that the leaf at `index` is part of this tree.
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.levels[0].len() {
+            return None;
+        }
+
+        let mut steps = Vec::new();
+        let mut position = index;
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                steps.push(ProofStep {
+                    hash: format!("{}", HexFmt(hash)),
+                    side: if sibling < position {
+                        Side::Left
+                    } else {
+                        Side::Right
+                    },
+                });
+            }
+            position /= 2;
+        }
+
+        Some(MerkleProof { index, steps })
+    }
+
+    // ---
+    // Private methods
+    // ---
+
+    fn hash_leaf(leaf: &[u8]) -> Digest32 {
+        let mut hasher = Sha256::new();
+        hasher.update([LEAF_PREFIX]);
+        hasher.update(leaf);
+        hasher.finalize().into()
+    }
+
+    fn hash_node(left: &Digest32, right: &Digest32) -> Digest32 {
+        let mut hasher = Sha256::new();
+        hasher.update([NODE_PREFIX]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub hash: String,
+    pub side: Side,
+}
+
+/// Sibling hashes from a leaf up to the root, ordered leaf first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub steps: Vec<ProofStep>,
+}
+
+impl MerkleProof {
+    /// Check that `leaf`, e.g. a transaction hash, hashes up to `root`.
+    pub fn verify(&self, leaf: impl AsRef<[u8]>, root: &str) -> bool {
+        let mut hash = MerkleTree::hash_leaf(leaf.as_ref());
+
+        for step in &self.steps {
+            let sibling: Digest32 = match hex::decode(&step.hash)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+            {
+                Some(sibling) => sibling,
+                None => return false,
+            };
+
+            hash = match step.side {
+                Side::Left => MerkleTree::hash_node(&sibling, &hash),
+                Side::Right => MerkleTree::hash_node(&hash, &sibling),
+            };
+        }
+
+        format!("{}", HexFmt(hash)) == root
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leaves(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("tx_hash_{i}")).collect()
+    }
+
+    #[test]
+    fn root_covers_every_leaf() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let root = MerkleTree::new(&leaves).root();
+
+            // Tampering with any single leaf changes the root
+            for i in 0..count {
+                let mut tampered = leaves.clone();
+                tampered[i] = "tampered".to_string();
+                assert_ne!(MerkleTree::new(&tampered).root(), root);
+            }
+        }
+    }
+
+    #[test]
+    fn no_duplication_collision() {
+        let leaves = leaves(3);
+        let mut duplicated = leaves.clone();
+        duplicated.push(leaves[2].clone());
+
+        assert_ne!(
+            MerkleTree::new(&leaves).root(),
+            MerkleTree::new(&duplicated).root()
+        );
+    }
+
+    #[test]
+    fn leaf_is_not_a_node() {
+        let leaves = leaves(2);
+        let tree = MerkleTree::new(&leaves);
+
+        // A single leaf tree is just the leaf hash, distinct from any node hash
+        let node = MerkleTree::hash_node(&tree.levels[0][0], &tree.levels[0][1]);
+        assert_eq!(tree.root(), format!("{}", HexFmt(node)));
+        assert_ne!(MerkleTree::new(&leaves[..1]).root(), tree.root());
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = MerkleTree::new(Vec::<String>::new());
+
+        assert_eq!(tree.root(), "0".repeat(64));
+        assert!(tree.proof(0).is_none());
+    }
+
+    #[test]
+    fn proofs_verify() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let tree = MerkleTree::new(&leaves);
+            let root = tree.root();
+
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify(leaf, &root));
+                assert!(!proof.verify("other", &root));
+            }
+            assert!(tree.proof(count).is_none());
+        }
+    }
+
+    #[test]
+    fn tampered_proof_fails() {
+        let leaves = leaves(5);
+        let tree = MerkleTree::new(&leaves);
+        let root = tree.root();
+
+        let mut proof = tree.proof(1).unwrap();
+        proof.steps[0].side = Side::Right;
+        assert!(!proof.verify(&leaves[1], &root));
+
+        let mut proof = tree.proof(1).unwrap();
+        proof.steps[1].hash = "00".repeat(32);
+        assert!(!proof.verify(&leaves[1], &root));
+
+        let mut proof = tree.proof(1).unwrap();
+        proof.steps[1].hash = "not hex".to_string();
+        assert!(!proof.verify(&leaves[1], &root));
+    }
+}
@@ -1,7 +1,20 @@
+pub mod amount;
 pub mod block;
+pub mod branches;
 pub mod chain;
 pub mod config;
+pub mod difficulty;
+pub mod error;
+pub mod genesis;
 pub mod hasher;
+pub mod index;
+pub mod mempool;
+pub mod merkle;
 pub mod models;
+pub mod query;
+pub mod spec;
+pub mod state;
+pub mod target;
 pub mod transaction;
 mod utils;
+pub mod validation;
@@ -1,14 +1,42 @@
 use std::fmt::Display;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use super::amount::Amount;
+
+/// Serialized untagged in JSON, as `{"sender": .., "receiver": .., ..}`, and
+/// tagged with its variant in bincode, which cannot tell untagged variants
+/// apart.
 #[derive(Clone, Deserialize, Serialize, Debug)]
-#[serde(untagged)]
+#[serde(remote = "Self")]
 pub enum TransactionData {
     TransferData {
         sender: String,
         receiver: String,
-        amount: f64,
+        amount: Amount,
+        /// Paid to the miner of the block including the transfer, on top of
+        /// the amount sent.
+        fee: Amount,
+        /// Number of transfers the sender made before this one, so each
+        /// signed transfer can only be applied once.
+        nonce: u64,
+    },
+    LoginData {
+        user: String,
+        timestamp: u64,
+    },
+}
+
+// Untagged shape of `TransactionData`, used by human readable formats
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "TransactionData", untagged)]
+enum UntaggedTransactionData {
+    TransferData {
+        sender: String,
+        receiver: String,
+        amount: Amount,
+        fee: Amount,
+        nonce: u64,
     },
     LoginData {
         user: String,
@@ -16,15 +44,85 @@ pub enum TransactionData {
     },
 }
 
+impl Serialize for TransactionData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            UntaggedTransactionData::serialize(self, serializer)
+        } else {
+            TransactionData::serialize(self, serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactionData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            UntaggedTransactionData::deserialize(deserializer)
+        } else {
+            TransactionData::deserialize(deserializer)
+        }
+    }
+}
+
+impl TransactionData {
+    /// The party that has to sign a transaction carrying this data.
+    pub fn sender(&self) -> &str {
+        match self {
+            Self::TransferData { sender, .. } => sender,
+            Self::LoginData { user, .. } => user,
+        }
+    }
+
+    pub fn receiver(&self) -> Option<&str> {
+        match self {
+            Self::TransferData { receiver, .. } => Some(receiver),
+            Self::LoginData { .. } => None,
+        }
+    }
+
+    pub fn amount(&self) -> Option<Amount> {
+        match self {
+            Self::TransferData { amount, .. } => Some(*amount),
+            Self::LoginData { .. } => None,
+        }
+    }
+
+    pub fn nonce(&self) -> Option<u64> {
+        match self {
+            Self::TransferData { nonce, .. } => Some(*nonce),
+            Self::LoginData { .. } => None,
+        }
+    }
+
+    /// Fee offered to the miner, zero for data other than transfers.
+    pub fn fee(&self) -> Amount {
+        match self {
+            Self::TransferData { fee, .. } => *fee,
+            Self::LoginData { .. } => Amount::ZERO,
+        }
+    }
+}
+
 impl Display for TransactionData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &*self {
+        match self {
             Self::TransferData {
                 sender,
                 receiver,
                 amount,
+                fee,
+                nonce,
             } => {
-                write!(f, "sender:{sender}|receiver:{receiver}|amount:{amount}",)
+                write!(
+                    f,
+                    "sender:{sender}|receiver:{receiver}|amount:{amount}|fee:{fee}|nonce:{nonce}",
+                )
             }
             Self::LoginData { user, timestamp } => {
                 write!(f, "user:{user}|timestamp:{timestamp}",)
@@ -42,12 +140,52 @@ mod test {
         let tx_data = TransactionData::TransferData {
             sender: "me".to_string(),
             receiver: "you".to_string(),
-            amount: 10.1,
+            amount: "10.1".parse().unwrap(),
+            fee: "0.5".parse().unwrap(),
+            nonce: 3,
         };
 
         assert_eq!(
             format!("{tx_data}"),
-            format!("sender:me|receiver:you|amount:10.1")
+            format!("sender:me|receiver:you|amount:10.10000000|fee:0.50000000|nonce:3")
         );
     }
+
+    #[test]
+    fn serde_transfer_data() {
+        let tx_data = TransactionData::TransferData {
+            sender: "me".to_string(),
+            receiver: "you".to_string(),
+            amount: "10.1".parse().unwrap(),
+            fee: "0.5".parse().unwrap(),
+            nonce: 3,
+        };
+
+        // The HTTP API shows the fields without the variant name
+        let json = serde_json::to_value(&tx_data).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "sender": "me",
+                "receiver": "you",
+                "amount": "10.10000000",
+                "fee": "0.50000000",
+                "nonce": 3
+            })
+        );
+        let parsed: TransactionData = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.to_string(), tx_data.to_string());
+
+        let login: TransactionData =
+            serde_json::from_str(r#"{"user": "me", "timestamp": 5}"#).unwrap();
+        assert!(matches!(
+            login,
+            TransactionData::LoginData { timestamp: 5, .. }
+        ));
+
+        // Stored blocks keep the variant so they read back
+        let bytes = bincode::serialize(&tx_data).unwrap();
+        let parsed: TransactionData = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(parsed.to_string(), tx_data.to_string());
+    }
 }
@@ -0,0 +1,197 @@
+use serde::Deserialize;
+
+use super::block::Block;
+
+/// Blocks returned by a query when no limit is given.
+pub const DEFAULT_LIMIT: usize = 20;
+/// Upper bound on the blocks returned by a single query.
+pub const MAX_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    /// Oldest block first.
+    #[default]
+    Asc,
+    /// Newest block first.
+    Desc,
+}
+
+/// Page of blocks to list, starting at height `from` and walking the chain in
+/// `order`. Only blocks matching every filter that is set are returned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockQuery {
+    /// First height to look at, genesis or the tip depending on the order if
+    /// not given.
+    pub from: Option<usize>,
+    pub limit: usize,
+    pub order: Order,
+    /// Earliest block timestamp, inclusive.
+    pub since: Option<u64>,
+    /// Latest block timestamp, inclusive.
+    pub until: Option<u64>,
+    /// Address paid the block reward.
+    pub miner: Option<String>,
+}
+
+impl Default for BlockQuery {
+    fn default() -> Self {
+        BlockQuery {
+            from: None,
+            limit: DEFAULT_LIMIT,
+            order: Order::Asc,
+            since: None,
+            until: None,
+            miner: None,
+        }
+    }
+}
+
+impl BlockQuery {
+    pub fn matches(&self, block: &Block) -> bool {
+        let timestamp = block.header.timestamp;
+
+        self.since.is_none_or(|since| timestamp >= since)
+            && self.until.is_none_or(|until| timestamp <= until)
+            && self
+                .miner
+                .as_deref()
+                .is_none_or(|miner| block.miner() == Some(miner))
+    }
+
+    /// Heights to look at, in order, on a chain of `height` blocks.
+    pub fn heights(&self, height: usize) -> Box<dyn Iterator<Item = usize>> {
+        match self.order {
+            Order::Asc => Box::new(self.from.unwrap_or(0)..height),
+            Order::Desc => {
+                let end = self
+                    .from
+                    .map_or(height, |from| from.saturating_add(1).min(height));
+                Box::new((0..end).rev())
+            }
+        }
+    }
+
+    /// Limit clamped to `1..=MAX_LIMIT`.
+    pub fn limit(&self) -> usize {
+        self.limit.clamp(1, MAX_LIMIT)
+    }
+}
+
+pub struct BlockPage<'a> {
+    pub blocks: Vec<&'a Block>,
+    /// Height to continue from to get the next page, if any blocks are left.
+    pub next: Option<usize>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{
+        amount::Amount,
+        chain::Chain,
+        models::TransactionData,
+        target::Target,
+        transaction::{Transaction, TransactionType},
+    };
+
+    fn reward(receiver: &str) -> Transaction {
+        let tx_data = TransactionData::TransferData {
+            sender: "Root".to_string(),
+            receiver: receiver.to_string(),
+            amount: Amount::from_coins(1).unwrap(),
+            fee: Amount::ZERO,
+            nonce: 0,
+        };
+        Chain::new_transaction(tx_data, TransactionType::Reward)
+    }
+
+    fn block(timestamp: u64, miner: &str) -> Block {
+        let mut block = Block::new(
+            1,
+            0,
+            vec![reward(miner)],
+            "merkle",
+            "prev_hash",
+            Target::MAX,
+            Amount::ZERO,
+        );
+        block.header.timestamp = timestamp;
+        block
+    }
+
+    #[test]
+    fn match_filters() {
+        let block = block(10, "alice");
+        assert!(BlockQuery::default().matches(&block));
+
+        let query = BlockQuery {
+            since: Some(10),
+            until: Some(10),
+            miner: Some("alice".to_string()),
+            ..BlockQuery::default()
+        };
+        assert!(query.matches(&block));
+
+        let since = BlockQuery {
+            since: Some(11),
+            ..BlockQuery::default()
+        };
+        assert!(!since.matches(&block));
+
+        let until = BlockQuery {
+            until: Some(9),
+            ..BlockQuery::default()
+        };
+        assert!(!until.matches(&block));
+
+        let miner = BlockQuery {
+            miner: Some("bob".to_string()),
+            ..BlockQuery::default()
+        };
+        assert!(!miner.matches(&block));
+    }
+
+    #[test]
+    fn heights_in_order() {
+        let asc = BlockQuery {
+            from: Some(2),
+            ..BlockQuery::default()
+        };
+        assert_eq!(asc.heights(5).collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(asc.heights(2).count(), 0);
+
+        let desc = BlockQuery {
+            from: Some(2),
+            order: Order::Desc,
+            ..BlockQuery::default()
+        };
+        assert_eq!(desc.heights(5).collect::<Vec<_>>(), [2, 1, 0]);
+
+        let tip = BlockQuery {
+            from: Some(10),
+            order: Order::Desc,
+            ..BlockQuery::default()
+        };
+        assert_eq!(tip.heights(3).collect::<Vec<_>>(), [2, 1, 0]);
+
+        let last = BlockQuery {
+            from: Some(usize::MAX),
+            order: Order::Desc,
+            ..BlockQuery::default()
+        };
+        assert_eq!(last.heights(3).collect::<Vec<_>>(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn clamp_limit() {
+        let query = |limit| BlockQuery {
+            limit,
+            ..BlockQuery::default()
+        };
+
+        assert_eq!(query(0).limit(), 1);
+        assert_eq!(query(5).limit(), 5);
+        assert_eq!(query(1000).limit(), MAX_LIMIT);
+    }
+}
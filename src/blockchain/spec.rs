@@ -0,0 +1,316 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::amount::{Amount, COIN};
+use super::block::Block;
+use super::config::{BlockLimits, ChainConfig};
+use super::genesis::{Allocation, GenesisError, GenesisSpec};
+use super::mempool::MempoolConfig;
+use crate::wallet::Address;
+
+/// Names of the built in networks, as given to `ChainSpec::preset`.
+pub const PRESETS: [&str; 2] = ["devnet", "testnet"];
+
+const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+const DEVNET_ADDRESS: &str = "rc1HbLD4Gk1ZYi1sLwsANiKy7JWWUWzfP6b5";
+const DEVNET_GENESIS_HASH: &str =
+    "0005e3fa8269bce192dfee4c0a040bda89e56553ac0bd64b123379604e7c1460";
+const TESTNET_ADDRESS: &str = "rt1HbLD4Gk1ZYi1sLwsANiKy7JWWUWyt3dYJ";
+const TESTNET_GENESIS_HASH: &str =
+    "0000f94d5b2566bcb45d6fe1eccc3a5d99b0d0f41039b0e5cf780241b00b6705";
+
+/// Everything that sets a network apart, shared by all of its nodes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChainSpec {
+    /// Name of the network, for people.
+    pub name: String,
+    /// Human readable start of every address on the network.
+    pub address_prefix: String,
+    /// Genesis block, which also carries the chain id.
+    pub genesis: GenesisSpec,
+    pub consensus: ConsensusParams,
+    pub rewards: RewardSchedule,
+    #[serde(default)]
+    pub limits: BlockLimits,
+}
+
+/// How blocks are paced.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConsensusParams {
+    /// Seconds the chain aims to take per block.
+    pub target_block_time: u64,
+    /// Number of blocks between difficulty adjustments, 0 disables them.
+    pub retarget_window: usize,
+    /// Let the difficulty and reward be changed at runtime, for local testing.
+    #[serde(default)]
+    pub dev_mode: bool,
+}
+
+/// Coins paid to miners on top of fees.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RewardSchedule {
+    /// Reward of the first block after genesis.
+    pub initial: Amount,
+    /// Number of blocks after which the reward halves, 0 keeps it fixed.
+    #[serde(default)]
+    pub halving_interval: usize,
+}
+
+#[derive(Debug)]
+pub enum SpecError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    UnknownFormat,
+    InvalidPrefix(String),
+    InvalidAllocation(String),
+    Genesis(GenesisError),
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Unable to read chain spec: {err}"),
+            Self::Toml(err) => write!(f, "Invalid chain spec: {err}"),
+            Self::Json(err) => write!(f, "Invalid chain spec: {err}"),
+            Self::UnknownFormat => write!(f, "Chain spec must be a .toml or .json file"),
+            Self::InvalidPrefix(prefix) => write!(f, "Invalid address prefix '{prefix}'"),
+            Self::InvalidAllocation(address) => {
+                write!(
+                    f,
+                    "Genesis allocation to {address} is not a network address"
+                )
+            }
+            Self::Genesis(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+impl ChainSpec {
+    /// Read a spec from the TOML or JSON file at `path`, told apart by the
+    /// file extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SpecError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(SpecError::Io)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(SpecError::Toml),
+            Some("json") => serde_json::from_str(&text).map_err(SpecError::Json),
+            _ => Err(SpecError::UnknownFormat),
+        }
+    }
+
+    /// Built in spec of the network called `name`, one of `PRESETS`.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "devnet" => Some(Self::devnet()),
+            "testnet" => Some(Self::testnet()),
+            _ => None,
+        }
+    }
+
+    /// Local network at a low fixed difficulty, which can be changed at runtime.
+    pub fn devnet() -> Self {
+        ChainSpec {
+            name: "devnet".to_string(),
+            address_prefix: "rc".to_string(),
+            genesis: GenesisSpec {
+                chain_id: "rust-chain-devnet".to_string(),
+                timestamp: GENESIS_TIMESTAMP,
+                difficulty: 3,
+                allocations: vec![Allocation {
+                    address: DEVNET_ADDRESS.to_string(),
+                    amount: Amount::from_base_units(10 * COIN),
+                }],
+                extra_data: String::new(),
+                hash: Some(DEVNET_GENESIS_HASH.to_string()),
+            },
+            consensus: ConsensusParams {
+                target_block_time: 10,
+                retarget_window: 10,
+                dev_mode: true,
+            },
+            rewards: RewardSchedule {
+                initial: Amount::from_base_units(10 * COIN),
+                halving_interval: 0,
+            },
+            limits: BlockLimits::default(),
+        }
+    }
+
+    /// Shared test network, retargeting its difficulty and halving its reward.
+    pub fn testnet() -> Self {
+        ChainSpec {
+            name: "testnet".to_string(),
+            address_prefix: "rt".to_string(),
+            genesis: GenesisSpec {
+                chain_id: "rust-chain-testnet".to_string(),
+                timestamp: GENESIS_TIMESTAMP,
+                difficulty: 4,
+                allocations: vec![Allocation {
+                    address: TESTNET_ADDRESS.to_string(),
+                    amount: Amount::from_base_units(50 * COIN),
+                }],
+                extra_data: "rust-chain testnet".to_string(),
+                hash: Some(TESTNET_GENESIS_HASH.to_string()),
+            },
+            consensus: ConsensusParams {
+                target_block_time: 10,
+                retarget_window: 10,
+                dev_mode: false,
+            },
+            rewards: RewardSchedule {
+                initial: Amount::from_base_units(50 * COIN),
+                halving_interval: 100_000,
+            },
+            limits: BlockLimits::default(),
+        }
+    }
+
+    /// Devnet with the chain id "test" and an unpinned genesis block paying
+    /// `amount` to `address`, for tests to adjust.
+    pub fn test(address: impl Into<String>, amount: Amount) -> Self {
+        let mut spec = Self::devnet();
+        spec.name = "test".to_string();
+        spec.genesis.chain_id = "test".to_string();
+        spec.genesis.allocations = vec![Allocation {
+            address: address.into(),
+            amount,
+        }];
+        spec.genesis.hash = None;
+        spec
+    }
+
+    pub fn chain_id(&self) -> &str {
+        &self.genesis.chain_id
+    }
+
+    /// Make sure the address prefix is usable and the genesis block pays out to
+    /// addresses of this network, then build the genesis block.
+    pub fn check(&self) -> Result<Block, SpecError> {
+        let prefix = &self.address_prefix;
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_lowercase()) {
+            return Err(SpecError::InvalidPrefix(prefix.clone()));
+        }
+        for allocation in &self.genesis.allocations {
+            if Address::parse_with_prefix(&allocation.address, prefix).is_err() {
+                return Err(SpecError::InvalidAllocation(allocation.address.clone()));
+            }
+        }
+        self.genesis.check().map_err(SpecError::Genesis)
+    }
+
+    /// Settings of a chain following this spec, with `mempool` limits of the
+    /// node's own choosing.
+    pub fn config(&self, mempool: MempoolConfig) -> ChainConfig {
+        ChainConfig {
+            difficulty: self.genesis.difficulty,
+            reward: self.rewards.initial,
+            halving_interval: self.rewards.halving_interval,
+            target_block_time: self.consensus.target_block_time,
+            retarget_window: self.consensus.retarget_window,
+            dev_mode: self.consensus.dev_mode,
+            address_prefix: self.address_prefix.clone(),
+            limits: self.limits,
+            mempool,
+            genesis: self.genesis.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        for name in PRESETS {
+            let spec = ChainSpec::preset(name).unwrap();
+            assert_eq!(spec.name, name);
+            assert!(spec.check().is_ok(), "{name} spec does not check out");
+        }
+        assert!(ChainSpec::preset("mainnet").is_none());
+
+        // Networks never share a chain id or genesis block
+        let (devnet, testnet) = (ChainSpec::devnet(), ChainSpec::testnet());
+        assert_ne!(devnet.chain_id(), testnet.chain_id());
+        assert_ne!(devnet.genesis.hash, testnet.genesis.hash);
+    }
+
+    #[test]
+    fn check_spec() {
+        let mut spec = ChainSpec::testnet();
+        spec.address_prefix = "RT".to_string();
+        assert!(matches!(spec.check(), Err(SpecError::InvalidPrefix(_))));
+
+        // Coins paid to a devnet address would be lost on testnet
+        let mut spec = ChainSpec::testnet();
+        spec.genesis.allocations[0].address = DEVNET_ADDRESS.to_string();
+        assert!(matches!(spec.check(), Err(SpecError::InvalidAllocation(_))));
+
+        let mut spec = ChainSpec::testnet();
+        spec.genesis.timestamp += 1;
+        assert!(matches!(
+            spec.check(),
+            Err(SpecError::Genesis(GenesisError::HashMismatch { .. }))
+        ));
+    }
+
+    #[test]
+    fn load_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec = ChainSpec::testnet();
+
+        let path = dir.path().join("testnet.json");
+        fs::write(&path, serde_json::to_string(&spec).unwrap()).unwrap();
+        assert_eq!(ChainSpec::load(&path).unwrap(), spec);
+
+        let path = dir.path().join("testnet.toml");
+        fs::write(&path, toml::to_string(&spec).unwrap()).unwrap();
+        assert_eq!(ChainSpec::load(&path).unwrap(), spec);
+
+        // Dev mode, the halving interval and the limits may be left out
+        let text = r#"
+            name = "local"
+            address_prefix = "rl"
+
+            [genesis]
+            chain_id = "local"
+            timestamp = 0
+            difficulty = 1
+            allocations = [{ address = "alice", amount = "2.5" }]
+
+            [consensus]
+            target_block_time = 5
+            retarget_window = 0
+
+            [rewards]
+            initial = "1"
+        "#;
+        fs::write(&path, text).unwrap();
+        let spec = ChainSpec::load(&path).unwrap();
+        assert!(!spec.consensus.dev_mode);
+        assert_eq!(spec.rewards.halving_interval, 0);
+        assert_eq!(spec.limits, BlockLimits::default());
+        assert_eq!(
+            spec.config(MempoolConfig::default()).reward_at(9),
+            "1".parse().unwrap()
+        );
+
+        fs::write(&path, "name = 1").unwrap();
+        assert!(matches!(ChainSpec::load(&path), Err(SpecError::Toml(_))));
+        let path = dir.path().join("spec.yaml");
+        fs::write(&path, "name: local").unwrap();
+        assert!(matches!(
+            ChainSpec::load(&path),
+            Err(SpecError::UnknownFormat)
+        ));
+        let missing = dir.path().join("missing.toml");
+        assert!(matches!(ChainSpec::load(missing), Err(SpecError::Io(_))));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::amount::{Amount, AmountError};
+use super::block::Block;
+use super::models::TransactionData;
+use super::transaction::{Transaction, TransactionType};
+
+/// Balances and nonces of every address, derived from confirmed transfers.
+#[derive(Clone, Default)]
+pub struct AccountState {
+    balances: HashMap<String, Amount>,
+    // Nonce of the next transfer from each sender
+    nonces: HashMap<String, u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StateError {
+    Amount(AmountError),
+    /// A transfer out of sequence for its sender, e.g. a replay.
+    NonceMismatch {
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Amount(err) => write!(f, "{err}"),
+            Self::NonceMismatch { expected, found } => {
+                write!(f, "nonce is {found}, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl From<AmountError> for StateError {
+    fn from(err: AmountError) -> Self {
+        Self::Amount(err)
+    }
+}
+
+impl AccountState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_blocks<'a>(
+        blocks: impl IntoIterator<Item = &'a Block>,
+    ) -> Result<Self, StateError> {
+        let mut state = AccountState::new();
+        for block in blocks {
+            state.apply_block(block)?;
+        }
+        Ok(state)
+    }
+
+    /// Apply every transaction of `block`. On error the state may be partially
+    /// updated, so callers apply blocks to a copy when they need to roll back.
+    pub fn apply_block(&mut self, block: &Block) -> Result<(), StateError> {
+        for tx in &block.txs {
+            self.apply_transaction(tx)?;
+        }
+        Ok(())
+    }
+
+    /// Credit the receiver of a transfer and, unless the coins are newly minted
+    /// by a reward, debit its sender the amount plus the fee and advance its
+    /// nonce. The fee reaches the miner through the block reward.
+    pub fn apply_transaction(&mut self, tx: &Transaction) -> Result<(), StateError> {
+        if let TransactionData::TransferData {
+            sender,
+            receiver,
+            amount,
+            fee,
+            nonce,
+        } = &tx.tx_data
+        {
+            if tx.tx_type == TransactionType::Transfer {
+                let expected = self.next_nonce(sender);
+                if *nonce != expected {
+                    return Err(StateError::NonceMismatch {
+                        expected,
+                        found: *nonce,
+                    });
+                }
+
+                let balance = self
+                    .balance_of(sender)
+                    .checked_sub(amount.checked_add(*fee)?)?;
+                self.balances.insert(sender.clone(), balance);
+                self.nonces.insert(sender.clone(), expected + 1);
+            }
+            let balance = self.balance_of(receiver).checked_add(*amount)?;
+            self.balances.insert(receiver.clone(), balance);
+        }
+        Ok(())
+    }
+
+    pub fn balance_of(&self, address: &str) -> Amount {
+        self.balances.get(address).copied().unwrap_or_default()
+    }
+
+    /// Nonce the next confirmed transfer from `address` has to carry, i.e. the
+    /// number of transfers it has sent so far.
+    pub fn next_nonce(&self, address: &str) -> u64 {
+        self.nonces.get(address).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{chain::Chain, target::Target};
+
+    fn coins(coins: u64) -> Amount {
+        Amount::from_coins(coins).unwrap()
+    }
+
+    fn transfer(
+        sender: &str,
+        receiver: &str,
+        amount: u64,
+        tx_type: TransactionType,
+    ) -> Transaction {
+        let tx_data = TransactionData::TransferData {
+            sender: sender.to_string(),
+            receiver: receiver.to_string(),
+            amount: coins(amount),
+            fee: Amount::ZERO,
+            nonce: 0,
+        };
+        Chain::new_transaction(tx_data, tx_type)
+    }
+
+    #[test]
+    fn apply_transactions() {
+        let mut state = AccountState::new();
+
+        let txs = [
+            transfer("Root", "alice", 10, TransactionType::GenesisReward),
+            transfer("Root", "bob", 5, TransactionType::Reward),
+            transfer("alice", "bob", 4, TransactionType::Transfer),
+        ];
+        for tx in &txs {
+            state.apply_transaction(tx).unwrap();
+        }
+
+        assert_eq!(state.balance_of("alice"), coins(6));
+        assert_eq!(state.balance_of("bob"), coins(9));
+        assert_eq!(state.balance_of("Root"), Amount::ZERO);
+        assert_eq!(state.balance_of("carol"), Amount::ZERO);
+    }
+
+    #[test]
+    fn apply_fee() {
+        let mut state = AccountState::new();
+        state
+            .apply_transaction(&transfer("Root", "alice", 10, TransactionType::Reward))
+            .unwrap();
+
+        let mut tx = transfer("alice", "bob", 4, TransactionType::Transfer);
+        if let TransactionData::TransferData { fee, .. } = &mut tx.tx_data {
+            *fee = coins(1);
+        }
+        state.apply_transaction(&tx).unwrap();
+
+        // The fee leaves the sender without reaching the receiver
+        assert_eq!(state.balance_of("alice"), coins(5));
+        assert_eq!(state.balance_of("bob"), coins(4));
+    }
+
+    #[test]
+    fn apply_overdraw() {
+        let mut state = AccountState::new();
+
+        let res = state.apply_transaction(&transfer("alice", "bob", 1, TransactionType::Transfer));
+
+        assert_eq!(res, Err(StateError::Amount(AmountError::Underflow)));
+    }
+
+    #[test]
+    fn from_blocks() {
+        let txs = vec![
+            transfer("Root", "alice", 10, TransactionType::Reward),
+            transfer("alice", "bob", 3, TransactionType::Transfer),
+        ];
+        let blocks = vec![
+            Block::new(0, 1, txs, "merkle", "prev_hash", Target::MAX, Amount::ZERO),
+            Block::new(
+                1,
+                1,
+                vec![transfer("bob", "carol", 1, TransactionType::Transfer)],
+                "merkle",
+                "prev_hash",
+                Target::MAX,
+                Amount::ZERO,
+            ),
+        ];
+
+        let state = AccountState::from_blocks(&blocks).unwrap();
+
+        assert_eq!(state.balance_of("alice"), coins(7));
+        assert_eq!(state.balance_of("bob"), coins(2));
+        assert_eq!(state.balance_of("carol"), coins(1));
+    }
+}
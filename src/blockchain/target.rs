@@ -0,0 +1,161 @@
+use std::fmt;
+
+use hex_fmt::HexFmt;
+use primitive_types::{U256, U512};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Number of bits in a hex digit, the unit difficulty is counted in.
+const BITS_PER_DIFFICULTY: usize = 4;
+
+/// Proof of work target, a block is valid when its header hash read as a big
+/// endian number is at most the target.
+///
+/// Human readable formats carry the target as 64 hex digits, binary formats as
+/// its 32 big endian bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Target(U256);
+
+impl Target {
+    /// Easiest possible target, met by every hash.
+    pub const MAX: Target = Target(U256::MAX);
+
+    /// Target requiring `difficulty` leading zero hex digits in the header hash.
+    pub fn from_difficulty(difficulty: usize) -> Self {
+        let shift = difficulty.saturating_mul(BITS_PER_DIFFICULTY);
+        if shift >= 256 {
+            return Target(U256::zero());
+        }
+        Target(U256::MAX >> shift)
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        Target(U256::from_big_endian(&bytes))
+    }
+
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        self.0.to_big_endian(&mut bytes);
+        bytes
+    }
+
+    /// Target multiplied by `numerator / denominator`, saturating at `MAX`.
+    pub fn scale(self, numerator: u64, denominator: u64) -> Self {
+        let scaled = self.0.full_mul(U256::from(numerator)) / U512::from(denominator.max(1));
+        Target(U256::try_from(scaled).unwrap_or(U256::MAX))
+    }
+
+    /// Expected number of hashes it takes to meet the target, which is the work
+    /// a block mined against it proves.
+    pub fn work(&self) -> U256 {
+        let hashes = (U512::one() << 256) / (U512::from(self.0) + U512::one());
+        U256::try_from(hashes).unwrap_or(U256::MAX)
+    }
+
+    /// Whether `hash` satisfies this target.
+    pub fn is_met_by(&self, hash: &[u8; 32]) -> bool {
+        U256::from_big_endian(hash) <= self.0
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", HexFmt(self.to_be_bytes()))
+    }
+}
+
+impl Serialize for Target {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.to_be_bytes().serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Target {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let string = String::deserialize(deserializer)?;
+            let bytes: [u8; 32] = hex::decode(&string)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| de::Error::custom("target must be 64 hex digits"))?;
+            Ok(Target::from_be_bytes(bytes))
+        } else {
+            <[u8; 32]>::deserialize(deserializer).map(Target::from_be_bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn target_from_difficulty() {
+        assert_eq!(Target::from_difficulty(0), Target::MAX);
+        assert_eq!(
+            Target::from_difficulty(3).to_string(),
+            format!("000{}", "f".repeat(61))
+        );
+        assert_eq!(Target::from_difficulty(64).to_string(), "0".repeat(64));
+        assert_eq!(Target::from_difficulty(100), Target::from_difficulty(64));
+        assert!(Target::from_difficulty(4) < Target::from_difficulty(3));
+    }
+
+    #[test]
+    fn scale_target() {
+        let target = Target::from_difficulty(2);
+
+        assert!(target.scale(4, 1) > target);
+        assert!(target.scale(4, 1) < Target::from_difficulty(1));
+        assert_eq!(target.scale(1, 16), Target::from_difficulty(3));
+        assert_eq!(target.scale(3, 3), target);
+        assert_eq!(Target::MAX.scale(4, 1), Target::MAX);
+    }
+
+    #[test]
+    fn target_is_met() {
+        let target = Target::from_difficulty(2);
+
+        let mut hash = [0xffu8; 32];
+        assert!(!target.is_met_by(&hash));
+
+        hash[0] = 0;
+        assert!(target.is_met_by(&hash));
+
+        hash[0] = 0x01;
+        assert!(!target.is_met_by(&hash));
+
+        assert!(Target::MAX.is_met_by(&[0xff; 32]));
+    }
+
+    #[test]
+    fn target_work() {
+        assert_eq!(Target::MAX.work(), U256::one());
+        assert_eq!(Target::from_difficulty(1).work(), U256::from(16));
+        assert_eq!(Target::from_difficulty(2).work(), U256::from(256));
+        assert_eq!(Target::from_difficulty(64).work(), U256::MAX);
+    }
+
+    #[test]
+    fn serde_target() {
+        let target = Target::from_difficulty(3);
+        let json = serde_json::to_string(&target).unwrap();
+
+        assert_eq!(json, format!("\"{target}\""));
+        assert_eq!(serde_json::from_str::<Target>(&json).unwrap(), target);
+        assert!(serde_json::from_str::<Target>("\"00ff\"").is_err());
+
+        let bytes = bincode::serialize(&target).unwrap();
+        assert_eq!(bytes, target.to_be_bytes());
+        assert_eq!(bincode::deserialize::<Target>(&bytes).unwrap(), target);
+    }
+}
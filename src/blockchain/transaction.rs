@@ -1,16 +1,21 @@
+use ed25519_dalek::{Signature, VerifyingKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::ops::{Deref, DerefMut};
 
-use super::hasher::Hash;
+use super::hasher::{Hash, Hasher};
 use super::models::TransactionData;
+use crate::wallet::Address;
 
-#[derive(Clone, Serialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Transaction {
     pub hash: Hash,
     pub timestamp: u64,
     pub status: TransactionStatus,
     pub tx_type: TransactionType,
     pub tx_data: TransactionData,
+    pub public_key: Option<String>,
+    pub signature: Option<String>,
 }
 
 impl Transaction {
@@ -26,15 +31,79 @@ impl Transaction {
             tx_data,
             tx_type,
             status: TransactionStatus::Created,
+            public_key: None,
+            signature: None,
         }
     }
 
-    pub fn verify(&self, _sender: &str, _signature: &str) -> bool {
-        // TODO: VERIFY SIGNATURE AGAINST tx_data
-        true
+    /// Whether `hash` is the hash of the transaction data and timestamp, which
+    /// it has to be before anything else about the transaction is trusted.
+    pub fn hash_matches(&self) -> bool {
+        let mut hash_buf = Hash::new();
+        Hasher::hash_tx_data(&self.tx_data, self.timestamp, &mut hash_buf).to_string()
+            == self.hash.to_string()
+    }
+
+    /// Canonical bytes covered by the sender's signature. They start with the
+    /// id of the chain the transaction is meant for, so a signed transaction
+    /// cannot be replayed on another network.
+    pub fn signing_payload(&self, chain_id: &str) -> Vec<u8> {
+        bincode::serialize(&(chain_id, &self.tx_data, self.tx_type, self.timestamp)).unwrap()
+    }
+
+    /// Verify `signature` over the signing payload for `chain_id` with the hex
+    /// encoded Ed25519 `public_key`, which must derive the sender address, with
+    /// `address_prefix`, in the transaction data.
+    pub fn verify(
+        &self,
+        chain_id: &str,
+        address_prefix: &str,
+        public_key: &str,
+        signature: &str,
+    ) -> Result<(), VerifyError> {
+        let key_bytes: [u8; PUBLIC_KEY_LENGTH] = hex::decode(public_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(VerifyError::InvalidPublicKey)?;
+        let key =
+            VerifyingKey::from_bytes(&key_bytes).map_err(|_| VerifyError::InvalidPublicKey)?;
+
+        if Address::with_prefix(&key, address_prefix).as_str() != self.tx_data.sender() {
+            return Err(VerifyError::SenderMismatch);
+        }
+
+        let sig_bytes: [u8; SIGNATURE_LENGTH] = hex::decode(signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(VerifyError::InvalidSignature)?;
+        let signature = Signature::from_bytes(&sig_bytes);
+
+        key.verify_strict(&self.signing_payload(chain_id), &signature)
+            .map_err(|_| VerifyError::SignatureMismatch)
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum VerifyError {
+    SenderMismatch,
+    InvalidPublicKey,
+    InvalidSignature,
+    SignatureMismatch,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SenderMismatch => write!(f, "public key does not match sender address"),
+            Self::InvalidPublicKey => write!(f, "public key is not a valid hex encoded key"),
+            Self::InvalidSignature => write!(f, "signature is not a valid hex encoded signature"),
+            Self::SignatureMismatch => write!(f, "signature does not match transaction"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
 #[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 pub enum TransactionStatus {
     Created,
@@ -65,7 +134,8 @@ pub enum TransactionType {
 #[cfg(test)]
 mod test {
     use super::*;
-    use test_utils::new_tx;
+    use crate::wallet::ADDRESS_PREFIX;
+    use test_utils::{new_tx, new_wallet_tx, Amount, Wallet};
 
     #[test]
     fn new_transfer_transaction() {
@@ -79,7 +149,7 @@ mod test {
         //     } => {
         //         assert!(sender == "me");
         //         assert!(receiver == "you");
-        //         assert!(amount == 10.0);
+        //         assert!(amount == Amount::from_coins(10).unwrap());
         //     }
         //     _ => (),
         // }
@@ -88,11 +158,15 @@ mod test {
             sender,
             receiver,
             amount,
+            fee,
+            nonce,
         } = tx.tx_data
         {
             assert!(sender == "me");
             assert!(receiver == "you");
-            assert!(amount == 10.0);
+            assert!(amount == Amount::from_coins(10).unwrap());
+            assert!(fee == Amount::ZERO);
+            assert!(nonce == 0);
         }
 
         assert_eq!(tx.tx_type, TransactionType::Transfer);
@@ -101,23 +175,104 @@ mod test {
 
     #[test]
     fn verify_transaction() {
-        let tx = new_tx();
+        let wallet = Wallet::from_secret(&[1u8; 32]);
+        let tx = new_wallet_tx(&wallet);
+
+        assert_eq!(
+            tx.verify(
+                "test",
+                ADDRESS_PREFIX,
+                &wallet.public_key(),
+                &wallet.sign(&tx, "test")
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn verify_transaction_errors() {
+        let wallet = Wallet::from_secret(&[1u8; 32]);
+        let other_wallet = Wallet::from_secret(&[2u8; 32]);
+        let public_key = wallet.public_key();
+        let tx = new_wallet_tx(&wallet);
+        let signature = wallet.sign(&tx, "test");
+
+        assert_eq!(
+            tx.verify(
+                "test",
+                ADDRESS_PREFIX,
+                &other_wallet.public_key(),
+                &signature
+            ),
+            Err(VerifyError::SenderMismatch)
+        );
+        assert_eq!(
+            tx.verify("test", ADDRESS_PREFIX, "me", &signature),
+            Err(VerifyError::InvalidPublicKey)
+        );
+        assert_eq!(
+            tx.verify("test", ADDRESS_PREFIX, &public_key, "signature"),
+            Err(VerifyError::InvalidSignature)
+        );
+        assert_eq!(
+            tx.verify(
+                "test",
+                ADDRESS_PREFIX,
+                &public_key,
+                &other_wallet.sign(&tx, "test")
+            ),
+            Err(VerifyError::SignatureMismatch)
+        );
+
+        // Signature does not carry over to a tampered transaction
+        let mut tampered = tx.clone();
+        tampered.timestamp += 1;
+        assert_eq!(
+            tampered.verify("test", ADDRESS_PREFIX, &public_key, &signature),
+            Err(VerifyError::SignatureMismatch)
+        );
 
-        assert!(tx.verify("me", "signature"));
+        // Nor to another network
+        assert_eq!(
+            tx.verify("other", ADDRESS_PREFIX, &public_key, &signature),
+            Err(VerifyError::SignatureMismatch)
+        );
+        // Where the sender has another address
+        assert_eq!(
+            tx.verify("test", "rt", &public_key, &signature),
+            Err(VerifyError::SenderMismatch)
+        );
     }
 
     mod test_utils {
         use super::*;
+        pub use crate::blockchain::amount::Amount;
         use crate::blockchain::{
             hasher::{Hash, Hasher},
             utils::timestamp,
         };
+        pub use crate::wallet::Wallet;
+
+        pub fn new_wallet_tx(wallet: &Wallet) -> Transaction {
+            let tx_data = TransactionData::TransferData {
+                sender: wallet.address().to_string(),
+                receiver: "you".to_string(),
+                amount: Amount::from_coins(10).unwrap(),
+                fee: Amount::ZERO,
+                nonce: 0,
+            };
+            let mut hash_buf = Hash::new();
+            let hash = Hasher::hash_tx_data(&tx_data, 1, &mut hash_buf);
+            Transaction::new(tx_data, TransactionType::Transfer, 1, hash.to_owned())
+        }
 
         pub fn new_tx() -> Transaction {
             let tx_data = TransactionData::TransferData {
                 sender: "me".to_string(),
                 receiver: "you".to_string(),
-                amount: 10.0,
+                amount: Amount::from_coins(10).unwrap(),
+                fee: Amount::ZERO,
+                nonce: 0,
             };
             let timestamp = timestamp();
             let mut hash_buf = Hash::new();
@@ -0,0 +1,975 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+use super::amount::Amount;
+use super::block::{Block, BlockHeader};
+use super::chain::Chain;
+use super::config::ChainConfig;
+use super::difficulty::required_target;
+use super::hasher::Hasher;
+use super::state::{AccountState, StateError};
+use super::target::Target;
+use super::transaction::{Transaction, TransactionType};
+use super::utils::timestamp;
+
+/// Furthest ahead of local time, in seconds, a block may be dated.
+pub const MAX_FUTURE_BLOCK_TIME: u64 = 2 * 60 * 60;
+/// Number of blocks whose median timestamp the next block may not be dated
+/// before.
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// Reason a single block failed validation.
+#[derive(Debug, PartialEq)]
+pub enum BlockError {
+    IndexMismatch {
+        expected: usize,
+        found: usize,
+    },
+    TxCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    TxHashMismatch {
+        tx_hash: String,
+    },
+    TooManyTransactions {
+        max: usize,
+        found: usize,
+    },
+    TooLarge {
+        max: usize,
+        size: usize,
+    },
+    MerkleRootMismatch,
+    PreviousHashMismatch,
+    GenesisMismatch {
+        expected: String,
+        found: String,
+    },
+    TimestampBeforeMedian,
+    TimestampInFuture,
+    TargetMismatch,
+    InvalidProofOfWork,
+    RewardCount(usize),
+    RewardMismatch {
+        expected: Amount,
+        found: Amount,
+    },
+    BaseRewardMismatch {
+        expected: Amount,
+        found: Amount,
+    },
+    InvalidTransactionType {
+        tx_hash: String,
+    },
+    ZeroAmount {
+        tx_hash: String,
+    },
+    MissingSignature {
+        tx_hash: String,
+    },
+    InvalidSignature {
+        tx_hash: String,
+        reason: String,
+    },
+    InsufficientFunds {
+        tx_hash: String,
+    },
+    NonceMismatch {
+        tx_hash: String,
+        expected: u64,
+        found: u64,
+    },
+    DuplicateTransaction {
+        tx_hash: String,
+    },
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IndexMismatch { expected, found } => {
+                write!(f, "index is {found}, expected {expected}")
+            }
+            Self::TxCountMismatch { expected, found } => {
+                write!(
+                    f,
+                    "tx_count is {found}, block holds {expected} transactions"
+                )
+            }
+            Self::TxHashMismatch { tx_hash } => {
+                write!(f, "transaction {tx_hash} does not match its hash")
+            }
+            Self::TooManyTransactions { max, found } => {
+                write!(f, "block holds {found} transactions, at most {max} allowed")
+            }
+            Self::TooLarge { max, size } => {
+                write!(f, "block is {size} bytes, at most {max} allowed")
+            }
+            Self::MerkleRootMismatch => write!(f, "merkle root does not match transactions"),
+            Self::PreviousHashMismatch => write!(f, "previous hash does not match parent block"),
+            Self::GenesisMismatch { expected, found } => {
+                write!(
+                    f,
+                    "genesis block is {found}, the genesis spec gives {expected}"
+                )
+            }
+            Self::TimestampBeforeMedian => {
+                write!(f, "timestamp is before the median of recent blocks")
+            }
+            Self::TimestampInFuture => write!(f, "timestamp is too far in the future"),
+            Self::TargetMismatch => write!(f, "target does not match required difficulty"),
+            Self::InvalidProofOfWork => write!(f, "header hash does not meet recorded target"),
+            Self::RewardCount(count) => {
+                write!(f, "block has {count} reward transactions, expected 1")
+            }
+            Self::RewardMismatch { expected, found } => {
+                write!(f, "reward is {found}, expected {expected}")
+            }
+            Self::BaseRewardMismatch { expected, found } => {
+                write!(
+                    f,
+                    "base reward is {found}, the reward schedule gives {expected}"
+                )
+            }
+            Self::InvalidTransactionType { tx_hash } => {
+                write!(f, "transaction {tx_hash} has an unexpected type")
+            }
+            Self::ZeroAmount { tx_hash } => {
+                write!(f, "transaction {tx_hash} transfers nothing")
+            }
+            Self::MissingSignature { tx_hash } => {
+                write!(f, "transaction {tx_hash} is not signed")
+            }
+            Self::InvalidSignature { tx_hash, reason } => {
+                write!(f, "transaction {tx_hash} not verified: {reason}")
+            }
+            Self::InsufficientFunds { tx_hash } => {
+                write!(f, "transaction {tx_hash} spends more than the sender holds")
+            }
+            Self::DuplicateTransaction { tx_hash } => {
+                write!(f, "transaction {tx_hash} is already in the chain")
+            }
+            Self::NonceMismatch {
+                tx_hash,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "transaction {tx_hash} has nonce {found}, expected {expected}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+impl Serialize for BlockError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ValidationFailure {
+    pub block_index: usize,
+    pub error: BlockError,
+}
+
+impl fmt::Display for ValidationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {}: {}", self.block_index, self.error)
+    }
+}
+
+/// Outcome of validating a whole chain, listing every failure found.
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub height: usize,
+    pub valid: bool,
+    pub failures: Vec<ValidationFailure>,
+}
+
+impl ValidationReport {
+    pub fn first_failure(&self) -> Option<&ValidationFailure> {
+        self.failures.first()
+    }
+}
+
+/// Check `blocks`, in chain order starting at genesis, against each other and
+/// against the rules used to mine them. Genesis must be the block built from
+/// the genesis spec.
+///
+/// In dev mode the difficulty and reward can be changed by hand, so targets
+/// and rewards are not checked against the retargeting and halving rules.
+pub fn validate_blocks<'a>(
+    config: &ChainConfig,
+    blocks: impl IntoIterator<Item = &'a Block>,
+) -> ValidationReport {
+    let blocks: Vec<&Block> = blocks.into_iter().collect();
+    let mut failures = Vec::new();
+    let mut accounts = AccountState::new();
+    let mut confirmed = HashSet::new();
+
+    let genesis = config.genesis.block().hash();
+    if let Some(found) = blocks.first().map(|block| block.hash()) {
+        if found != genesis {
+            failures.push(ValidationFailure {
+                block_index: 0,
+                error: BlockError::GenesisMismatch {
+                    expected: genesis,
+                    found,
+                },
+            });
+        }
+    }
+
+    for (position, block) in blocks.iter().enumerate() {
+        let block_at = |index: usize| blocks.get(index).copied();
+        let required = (!config.dev_mode).then(|| required_target(config, position, block_at));
+        let errors = validate_block(
+            config,
+            block,
+            position,
+            block_at,
+            required,
+            &mut accounts,
+            |tx_hash| confirmed.contains(tx_hash),
+        );
+        confirmed.extend(block.txs.iter().map(|tx| tx.hash.to_string()));
+
+        failures.extend(errors.into_iter().map(|error| ValidationFailure {
+            block_index: position,
+            error,
+        }));
+    }
+
+    ValidationReport {
+        height: blocks.len(),
+        valid: failures.is_empty(),
+        failures,
+    }
+}
+
+/// Check a single `block` at `position` against the rules of `config` and the
+/// blocks below it, which `block_at` looks up by index, applying its
+/// transactions to `accounts`. The recorded target is only checked when a
+/// `required_target` is given, and `is_confirmed` tells whether a transaction
+/// hash is already in an earlier block.
+///
+/// Without a parent the block is taken as genesis, whose parent hash is up to
+/// the genesis spec.
+pub fn validate_block<'a>(
+    config: &ChainConfig,
+    block: &Block,
+    position: usize,
+    block_at: impl Fn(usize) -> Option<&'a Block>,
+    required_target: Option<Target>,
+    accounts: &mut AccountState,
+    is_confirmed: impl Fn(&str) -> bool,
+) -> Vec<BlockError> {
+    let mut errors = Vec::new();
+    let header = &block.header;
+
+    if header.index != position {
+        errors.push(BlockError::IndexMismatch {
+            expected: position,
+            found: header.index,
+        });
+    }
+
+    if block.tx_count != block.txs.len() {
+        errors.push(BlockError::TxCountMismatch {
+            expected: block.txs.len(),
+            found: block.tx_count,
+        });
+    }
+
+    let limits = config.limits;
+    if block.txs.len() > limits.max_block_txs {
+        errors.push(BlockError::TooManyTransactions {
+            max: limits.max_block_txs,
+            found: block.txs.len(),
+        });
+    }
+    let size = block.size();
+    if size > limits.max_block_bytes {
+        errors.push(BlockError::TooLarge {
+            max: limits.max_block_bytes,
+            size,
+        });
+    }
+
+    let mut seen = HashSet::new();
+    for tx in &block.txs {
+        let tx_hash = tx.hash.to_string();
+        if !tx.hash_matches() {
+            errors.push(BlockError::TxHashMismatch {
+                tx_hash: tx_hash.clone(),
+            });
+        }
+        if is_confirmed(&tx_hash) || !seen.insert(tx_hash.clone()) {
+            errors.push(BlockError::DuplicateTransaction { tx_hash });
+        }
+    }
+
+    if header.merkle_root != Hasher::merkle_root(&block.txs) {
+        errors.push(BlockError::MerkleRootMismatch);
+    }
+
+    let parent = position.checked_sub(1).and_then(&block_at);
+    if let Some(parent) = parent {
+        if header.previous_hash != parent.hash() {
+            errors.push(BlockError::PreviousHashMismatch);
+        }
+        // Rather than after the parent, so a block dated ahead of the others
+        // does not hold back the timestamps of the blocks after it
+        if median_time_past(position, &block_at).is_some_and(|median| header.timestamp < median) {
+            errors.push(BlockError::TimestampBeforeMedian);
+        }
+        if is_in_future(header) {
+            errors.push(BlockError::TimestampInFuture);
+        }
+    }
+
+    if required_target.is_some_and(|target| header.target != target) {
+        errors.push(BlockError::TargetMismatch);
+    }
+
+    if !Chain::valid_proof(block) {
+        errors.push(BlockError::InvalidProofOfWork);
+    }
+
+    // Genesis pays out its allocations rather than a scheduled reward. In dev
+    // mode the reward may have changed since, so the chain checks new blocks
+    // against the current one itself
+    let expected = config.reward_at(position);
+    if parent.is_some() && !config.dev_mode && header.reward != expected {
+        errors.push(BlockError::BaseRewardMismatch {
+            expected,
+            found: header.reward,
+        });
+    }
+
+    errors.extend(validate_transactions(config, block, parent.is_none()));
+
+    for tx in &block.txs {
+        let tx_hash = tx.hash.to_string();
+        match accounts.apply_transaction(tx) {
+            Ok(()) => {}
+            Err(StateError::NonceMismatch { expected, found }) => {
+                errors.push(BlockError::NonceMismatch {
+                    tx_hash,
+                    expected,
+                    found,
+                })
+            }
+            Err(StateError::Amount(_)) => errors.push(BlockError::InsufficientFunds { tx_hash }),
+        }
+    }
+
+    errors
+}
+
+/// Check that `headers` follow on from `parent` one after another, each
+/// meeting its recorded target and, when given, no easier than `easiest`.
+///
+/// Only the headers are looked at, their blocks are validated once fetched.
+/// That includes the median time check, which needs more than the parent.
+pub fn validate_headers(
+    parent: &BlockHeader,
+    headers: &[BlockHeader],
+    easiest: Option<Target>,
+) -> Result<(), BlockError> {
+    let mut parent = parent;
+    for header in headers {
+        if header.index != parent.index + 1 {
+            return Err(BlockError::IndexMismatch {
+                expected: parent.index + 1,
+                found: header.index,
+            });
+        }
+        if header.previous_hash != parent.hash() {
+            return Err(BlockError::PreviousHashMismatch);
+        }
+        if is_in_future(header) {
+            return Err(BlockError::TimestampInFuture);
+        }
+        if easiest.is_some_and(|easiest| header.target > easiest) {
+            return Err(BlockError::TargetMismatch);
+        }
+        if !header.meets_target() {
+            return Err(BlockError::InvalidProofOfWork);
+        }
+        parent = header;
+    }
+    Ok(())
+}
+
+/// Median timestamp of the up to `MEDIAN_TIME_SPAN` blocks below `height`,
+/// which `block_at` looks up by index, or `None` below genesis.
+pub fn median_time_past<'a>(
+    height: usize,
+    block_at: impl Fn(usize) -> Option<&'a Block>,
+) -> Option<u64> {
+    let mut times: Vec<u64> = (height.saturating_sub(MEDIAN_TIME_SPAN)..height)
+        .filter_map(block_at)
+        .map(|block| block.header.timestamp)
+        .collect();
+    times.sort_unstable();
+    times.get(times.len() / 2).copied()
+}
+
+// ---
+// Private methods
+// ---
+
+/// The block must hold exactly one reward, of the genesis kind only in the
+/// genesis block, paying the recorded reward plus the fees of the block, and
+/// every other transaction must be a transfer of a nonzero amount signed by its
+/// sender for the chain of `config`. Genesis may split its reward over several
+/// allocations.
+fn validate_transactions(config: &ChainConfig, block: &Block, is_genesis: bool) -> Vec<BlockError> {
+    let mut errors = Vec::new();
+    let reward_type = if is_genesis {
+        TransactionType::GenesisReward
+    } else {
+        TransactionType::Reward
+    };
+
+    let rewards: Vec<&Transaction> = block
+        .txs
+        .iter()
+        .filter(|tx| tx.tx_type == reward_type)
+        .collect();
+
+    if rewards.is_empty() || (!is_genesis && rewards.len() != 1) {
+        errors.push(BlockError::RewardCount(rewards.len()));
+    }
+
+    let expected = block
+        .txs
+        .iter()
+        .filter(|tx| tx.tx_type != reward_type)
+        .map(|tx| tx.tx_data.fee())
+        .fold(block.header.reward, Amount::saturating_add);
+
+    for reward in &rewards {
+        if reward.tx_data.sender() != "Root" {
+            errors.push(BlockError::InvalidTransactionType {
+                tx_hash: reward.hash.to_string(),
+            });
+        }
+        let amount = reward.tx_data.amount().unwrap_or_default();
+        if !is_genesis && amount != expected {
+            errors.push(BlockError::RewardMismatch {
+                expected,
+                found: amount,
+            });
+        }
+    }
+    if is_genesis {
+        let total = rewards
+            .iter()
+            .map(|reward| reward.tx_data.amount().unwrap_or_default())
+            .fold(Amount::ZERO, Amount::saturating_add);
+        if total != expected {
+            errors.push(BlockError::RewardMismatch {
+                expected,
+                found: total,
+            });
+        }
+    }
+
+    for tx in block.txs.iter().filter(|tx| tx.tx_type != reward_type) {
+        let tx_hash = tx.hash.to_string();
+
+        if tx.tx_type != TransactionType::Transfer {
+            errors.push(BlockError::InvalidTransactionType { tx_hash });
+            continue;
+        }
+        if tx.tx_data.amount() == Some(Amount::ZERO) {
+            errors.push(BlockError::ZeroAmount {
+                tx_hash: tx_hash.clone(),
+            });
+        }
+
+        match (&tx.public_key, &tx.signature) {
+            (Some(public_key), Some(signature)) => {
+                if let Err(err) = tx.verify(
+                    &config.genesis.chain_id,
+                    &config.address_prefix,
+                    public_key,
+                    signature,
+                ) {
+                    errors.push(BlockError::InvalidSignature {
+                        tx_hash,
+                        reason: err.to_string(),
+                    });
+                }
+            }
+            _ => errors.push(BlockError::MissingSignature { tx_hash }),
+        }
+    }
+
+    errors
+}
+
+/// Whether `header` is dated more than `MAX_FUTURE_BLOCK_TIME` ahead of local
+/// time. Blocks built on it would have to be dated as far ahead, so it is left
+/// out until local time catches up.
+fn is_in_future(header: &BlockHeader) -> bool {
+    header.timestamp > timestamp().saturating_add(MAX_FUTURE_BLOCK_TIME)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{
+        genesis::Allocation, hasher::Hash, mempool::MempoolConfig, models::TransactionData,
+        spec::ChainSpec,
+    };
+    use crate::storage::MemoryStore;
+    use crate::wallet::Wallet;
+
+    fn wallet() -> Wallet {
+        Wallet::from_secret(&[9u8; 32])
+    }
+
+    fn config() -> ChainConfig {
+        let mut spec = ChainSpec::test(
+            wallet().address().to_string(),
+            Amount::from_coins(10).unwrap(),
+        );
+        spec.genesis.difficulty = 1;
+        spec.consensus.retarget_window = 2;
+        spec.consensus.dev_mode = false;
+        spec.config(MempoolConfig::default())
+    }
+
+    /// Chain of three blocks, each after genesis holding one signed transfer
+    /// paying a fee of 0.5.
+    fn blocks() -> Vec<Block> {
+        let config = config();
+        let wallet = wallet();
+        let mut chain = Chain::new(config, wallet.address(), Box::new(MemoryStore::new()));
+
+        for nonce in 0..2 {
+            let tx_data = TransactionData::TransferData {
+                sender: wallet.address().to_string(),
+                receiver: "receiver".to_string(),
+                amount: Amount::from_coins(1).unwrap(),
+                fee: fee(),
+                nonce,
+            };
+            let mut tx = Chain::new_transaction(tx_data, TransactionType::Transfer);
+            let signature = wallet.sign(&tx, "test");
+            chain
+                .add_transaction(&mut tx, &wallet.public_key(), &signature)
+                .unwrap();
+            chain.mine_new_block().unwrap();
+        }
+
+        chain.blocks().into_iter().cloned().collect()
+    }
+
+    fn fee() -> Amount {
+        "0.5".parse().unwrap()
+    }
+
+    /// Solve the proof of work again after tampering with a header.
+    fn remine(block: &mut Block) {
+        while !Chain::valid_proof(block) {
+            block.header.nonce += 1;
+        }
+    }
+
+    fn errors(blocks: &[Block]) -> Vec<BlockError> {
+        validate_blocks(&config(), blocks)
+            .failures
+            .into_iter()
+            .map(|failure| failure.error)
+            .collect()
+    }
+
+    #[test]
+    fn valid_chain() {
+        let blocks = blocks();
+        let report = validate_blocks(&config(), &blocks);
+
+        assert!(report.valid);
+        assert_eq!(report.height, 3);
+        assert!(report.first_failure().is_none());
+    }
+
+    #[test]
+    fn tampered_header() {
+        let mut blocks = blocks();
+        blocks[1].header.timestamp += 1;
+        remine(&mut blocks[1]);
+
+        // The next block no longer links to the tampered one
+        let report = validate_blocks(&config(), &blocks);
+        assert_eq!(
+            report.first_failure(),
+            Some(&ValidationFailure {
+                block_index: 2,
+                error: BlockError::PreviousHashMismatch
+            })
+        );
+
+        let mut blocks = self::blocks();
+        blocks[2].header.index = 5;
+        remine(&mut blocks[2]);
+        assert_eq!(
+            errors(&blocks),
+            vec![BlockError::IndexMismatch {
+                expected: 2,
+                found: 5
+            }]
+        );
+
+        let mut blocks = self::blocks();
+        blocks[2].header.timestamp = blocks[1].header.timestamp - 1;
+        remine(&mut blocks[2]);
+        assert_eq!(errors(&blocks), vec![BlockError::TimestampBeforeMedian]);
+    }
+
+    #[test]
+    fn median_time() {
+        let blocks: Vec<Block> = [10, 50, 20, 40, 30]
+            .into_iter()
+            .map(|timestamp| {
+                let mut block = Block::new(0, 0, Vec::new(), "", "", Target::MAX, Amount::ZERO);
+                block.header.timestamp = timestamp;
+                block
+            })
+            .collect();
+        let block_at = |index: usize| blocks.get(index);
+
+        assert_eq!(median_time_past(0, block_at), None);
+        assert_eq!(median_time_past(1, block_at), Some(10));
+        assert_eq!(median_time_past(5, block_at), Some(30));
+        // A block dated ahead of the rest does not hold back the next one
+        assert_eq!(median_time_past(2, block_at), Some(50));
+        assert_eq!(median_time_past(3, block_at), Some(20));
+    }
+
+    #[test]
+    fn future_timestamp() {
+        let mut blocks = blocks();
+        blocks[2].header.timestamp = timestamp() + MAX_FUTURE_BLOCK_TIME + 60;
+        remine(&mut blocks[2]);
+        assert_eq!(errors(&blocks), vec![BlockError::TimestampInFuture]);
+
+        let headers: Vec<BlockHeader> = blocks.iter().map(|b| b.header.clone()).collect();
+        assert_eq!(
+            validate_headers(&headers[0], &headers[1..], None),
+            Err(BlockError::TimestampInFuture)
+        );
+
+        // Slightly ahead of local time is fine
+        blocks[2].header.timestamp = timestamp() + 60;
+        remine(&mut blocks[2]);
+        assert!(errors(&blocks).is_empty());
+    }
+
+    #[test]
+    fn invalid_proof_of_work() {
+        let mut blocks = blocks();
+        while Chain::valid_proof(&blocks[2]) {
+            blocks[2].header.nonce += 1;
+        }
+
+        assert_eq!(errors(&blocks), vec![BlockError::InvalidProofOfWork]);
+
+        // Loosening the recorded target breaks the link from the next block
+        let mut blocks = self::blocks();
+        blocks[1].header.target = Target::MAX;
+        assert!(errors(&blocks).contains(&BlockError::PreviousHashMismatch));
+    }
+
+    #[test]
+    fn genesis_from_spec() {
+        // Blocks stored for another genesis block fail on genesis
+        let blocks = blocks();
+        let mut other = config();
+        other.genesis.extra_data = "other".to_string();
+        let report = validate_blocks(&other, &blocks);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(
+            report.first_failure(),
+            Some(&ValidationFailure {
+                block_index: 0,
+                error: BlockError::GenesisMismatch {
+                    expected: other.genesis.block().hash(),
+                    found: blocks[0].hash(),
+                }
+            })
+        );
+
+        // Genesis may pay out to several addresses
+        let mut config = config();
+        config.genesis.allocations.push(Allocation {
+            address: "receiver".to_string(),
+            amount: Amount::from_coins(3).unwrap(),
+        });
+        let genesis = config.genesis.block();
+        assert!(validate_blocks(&config, [&genesis]).valid);
+
+        let mut tampered = genesis.clone();
+        tampered.header.reward = Amount::from_coins(12).unwrap();
+        remine(&mut tampered);
+        let errors: Vec<BlockError> = validate_blocks(&config, [&tampered])
+            .failures
+            .into_iter()
+            .map(|failure| failure.error)
+            .collect();
+        assert!(matches!(errors[0], BlockError::GenesisMismatch { .. }));
+        assert_eq!(
+            errors[1],
+            BlockError::RewardMismatch {
+                expected: Amount::from_coins(12).unwrap(),
+                found: Amount::from_coins(13).unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn header_chain() {
+        let blocks = blocks();
+        let headers: Vec<BlockHeader> = blocks.iter().map(|b| b.header.clone()).collect();
+        let easiest = Some(Target::from_difficulty(1));
+        let check = |headers: &[BlockHeader]| validate_headers(&headers[0], &headers[1..], easiest);
+
+        assert_eq!(check(&headers), Ok(()));
+        assert_eq!(validate_headers(&headers[0], &[], easiest), Ok(()));
+        assert_eq!(
+            validate_headers(&headers[0], &headers[2..], easiest),
+            Err(BlockError::IndexMismatch {
+                expected: 1,
+                found: 2
+            })
+        );
+
+        let mut tampered = headers.clone();
+        while tampered[1].meets_target() {
+            tampered[1].nonce += 1;
+        }
+        assert_eq!(check(&tampered), Err(BlockError::InvalidProofOfWork));
+        let mut block = blocks[1].clone();
+        block.header.timestamp += 1;
+        remine(&mut block);
+        tampered[1] = block.header;
+        assert_eq!(check(&tampered), Err(BlockError::PreviousHashMismatch));
+
+        // Cheap headers are refused unless in dev mode
+        let mut cheap = headers[..2].to_vec();
+        cheap[1].target = Target::MAX;
+        assert_eq!(check(&cheap), Err(BlockError::TargetMismatch));
+        assert_eq!(validate_headers(&cheap[0], &cheap[1..], None), Ok(()));
+    }
+
+    #[test]
+    fn tampered_transactions() {
+        let mut blocks = blocks();
+        if let TransactionData::TransferData { amount, .. } = &mut blocks[1].txs[0].tx_data {
+            *amount = Amount::from_coins(5).unwrap();
+        }
+
+        let errors = errors(&blocks);
+        let tx_hash = blocks[1].txs[0].hash.to_string();
+        assert!(errors.contains(&BlockError::TxHashMismatch {
+            tx_hash: tx_hash.clone()
+        }));
+        assert!(errors.contains(&BlockError::InvalidSignature {
+            tx_hash,
+            reason: "signature does not match transaction".to_string()
+        }));
+
+        let mut blocks = self::blocks();
+        blocks[1].txs[0].signature = None;
+        assert!(
+            self::errors(&blocks).contains(&BlockError::MissingSignature {
+                tx_hash: blocks[1].txs[0].hash.to_string()
+            })
+        );
+
+        let mut blocks = self::blocks();
+        blocks[1].txs.pop();
+        let errors = self::errors(&blocks);
+        assert!(errors.contains(&BlockError::MerkleRootMismatch));
+        assert!(errors.contains(&BlockError::RewardCount(0)));
+    }
+
+    #[test]
+    fn zero_amount() {
+        // Signed and hashed as the sender would, but refused by the mempool
+        let mut blocks = blocks();
+        let tx = &mut blocks[1].txs[0];
+        if let TransactionData::TransferData { amount, .. } = &mut tx.tx_data {
+            *amount = Amount::ZERO;
+        }
+        let mut hash_buf = Hash::new();
+        tx.hash = Hasher::hash_tx_data(&tx.tx_data, tx.timestamp, &mut hash_buf).to_owned();
+        tx.signature = Some(wallet().sign(tx, "test"));
+        let tx_hash = tx.hash.to_string();
+        blocks[1].header.merkle_root = Hasher::merkle_root(&blocks[1].txs);
+        remine(&mut blocks[1]);
+
+        assert_eq!(
+            errors(&blocks[..2]),
+            vec![BlockError::ZeroAmount { tx_hash }]
+        );
+    }
+
+    #[test]
+    fn replayed_transaction() {
+        let mut blocks = blocks();
+        let replay = blocks[1].txs[0].clone();
+        blocks[2].txs[0] = replay.clone();
+        blocks[2].header.merkle_root = Hasher::merkle_root(&blocks[2].txs);
+        remine(&mut blocks[2]);
+
+        let tx_hash = replay.hash.to_string();
+        assert_eq!(
+            errors(&blocks),
+            vec![
+                BlockError::DuplicateTransaction {
+                    tx_hash: tx_hash.clone()
+                },
+                BlockError::NonceMismatch {
+                    tx_hash,
+                    expected: 1,
+                    found: 0
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_transaction() {
+        let mut blocks = blocks();
+        let duplicate = blocks[2].txs[0].clone();
+        blocks[2].txs.insert(0, duplicate.clone());
+        blocks[2].tx_count += 1;
+        blocks[2].header.merkle_root = Hasher::merkle_root(&blocks[2].txs);
+        remine(&mut blocks[2]);
+
+        assert!(errors(&blocks).contains(&BlockError::DuplicateTransaction {
+            tx_hash: duplicate.hash.to_string()
+        }));
+
+        // A block repeating a transaction of an earlier one
+        let mut blocks = self::blocks();
+        let reward = blocks[1].txs.last().unwrap().clone();
+        *blocks[2].txs.last_mut().unwrap() = reward.clone();
+        blocks[2].header.merkle_root = Hasher::merkle_root(&blocks[2].txs);
+        remine(&mut blocks[2]);
+
+        assert!(
+            self::errors(&blocks).contains(&BlockError::DuplicateTransaction {
+                tx_hash: reward.hash.to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn reward_mismatch() {
+        let mut blocks = blocks();
+        let reward = blocks[1].txs.last_mut().unwrap();
+        if let TransactionData::TransferData { amount, .. } = &mut reward.tx_data {
+            *amount = Amount::from_coins(1_000).unwrap();
+        }
+
+        let expected = Amount::from_coins(10).unwrap().checked_add(fee()).unwrap();
+        assert!(errors(&blocks).contains(&BlockError::RewardMismatch {
+            expected,
+            found: Amount::from_coins(1_000).unwrap()
+        }));
+
+        // Fees of the block are owed to the miner on top of the base reward
+        let mut blocks = self::blocks();
+        let reward = blocks[1].txs.last_mut().unwrap();
+        if let TransactionData::TransferData { amount, .. } = &mut reward.tx_data {
+            *amount = Amount::from_coins(10).unwrap();
+        }
+
+        assert!(self::errors(&blocks).contains(&BlockError::RewardMismatch {
+            expected,
+            found: Amount::from_coins(10).unwrap()
+        }));
+    }
+
+    #[test]
+    fn reward_schedule() {
+        let blocks = blocks();
+        let mut config = config();
+        config.halving_interval = 1;
+
+        let report = validate_blocks(&config, &blocks);
+        assert_eq!(
+            report.failures,
+            [ValidationFailure {
+                block_index: 2,
+                error: BlockError::BaseRewardMismatch {
+                    expected: Amount::from_coins(5).unwrap(),
+                    found: Amount::from_coins(10).unwrap(),
+                },
+            }]
+        );
+
+        // The reward is up to the miner in dev mode
+        config.dev_mode = true;
+        assert!(validate_blocks(&config, &blocks).valid);
+    }
+
+    #[test]
+    fn block_limits() {
+        let blocks = blocks();
+        let mut config = config();
+        config.limits.max_block_txs = 1;
+        config.limits.max_block_bytes = blocks[1].size() - 1;
+
+        let errors: Vec<BlockError> = validate_blocks(&config, &blocks[..2])
+            .failures
+            .into_iter()
+            .map(|failure| failure.error)
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                BlockError::TooManyTransactions { max: 1, found: 2 },
+                BlockError::TooLarge {
+                    max: blocks[1].size() - 1,
+                    size: blocks[1].size(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn signed_for_other_chain() {
+        // Same blocks checked as another network's, genesis aside
+        let blocks = blocks();
+        let mut config = config();
+        config.genesis.chain_id = "other".to_string();
+
+        let errors: Vec<BlockError> = validate_blocks(&config, &blocks[1..2])
+            .failures
+            .into_iter()
+            .map(|failure| failure.error)
+            .collect();
+        assert!(errors.contains(&BlockError::InvalidSignature {
+            tx_hash: blocks[1].txs[0].hash.to_string(),
+            reason: "signature does not match transaction".to_string(),
+        }));
+    }
+}
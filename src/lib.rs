@@ -1,4 +1,7 @@
 pub mod app;
 pub mod blockchain;
+pub mod miner;
+pub mod p2p;
 pub mod services;
 pub mod storage;
+pub mod wallet;
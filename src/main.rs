@@ -4,23 +4,30 @@ use actix_web::{App, HttpServer};
 use std::io;
 
 use rust_chain::app::new_app_state;
+use rust_chain::services::account::register_account_service;
 use rust_chain::services::block::register_block_service;
 use rust_chain::services::chain::register_chain_service;
+use rust_chain::services::miner::register_miner_service;
+use rust_chain::services::mining::register_mining_service;
+use rust_chain::services::node::register_node_service;
 use rust_chain::services::transactions::register_transaction_service;
 
-const SERVER_HOST: (&str, u16) = ("127.0.0.1", 7878);
+const DEFAULT_SERVER_ADDR: &str = "127.0.0.1:7878";
 
 #[actix_web::main]
 async fn main() -> io::Result<()> {
+    dotenv::dotenv().ok();
+
     let app_state = new_app_state();
 
     std::env::set_var("RUST_LOG", "actix_web=info");
     env_logger::init();
 
-    println!(
-        "Server listening at {:}:{:}...",
-        SERVER_HOST.0, SERVER_HOST.1
-    );
+    // Nodes sharing a machine each need their own address
+    let server_addr =
+        std::env::var("SERVER_ADDR").unwrap_or_else(|_| DEFAULT_SERVER_ADDR.to_string());
+
+    println!("Server listening at {server_addr}...");
 
     // Make new HTTP server
     HttpServer::new(move || {
@@ -31,8 +38,12 @@ async fn main() -> io::Result<()> {
             .service(register_transaction_service())
             .service(register_block_service())
             .service(register_chain_service())
+            .service(register_account_service())
+            .service(register_miner_service())
+            .service(register_mining_service())
+            .service(register_node_service())
     })
-    .bind(SERVER_HOST)?
+    .bind(server_addr)?
     .run()
     .await
 }
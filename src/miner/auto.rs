@@ -0,0 +1,286 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::{mine_block, Miner};
+use crate::blockchain::chain::Chain;
+
+/// When the background loop produces a block.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MiningTrigger {
+    /// Whenever there are pending transactions.
+    Pending,
+    /// Every `secs` seconds, with or without pending transactions.
+    Interval { secs: u64 },
+    /// Once at least `tx_count` transactions are pending.
+    Threshold { tx_count: usize },
+}
+
+#[derive(Clone, Debug)]
+pub struct AutoMineConfig {
+    pub trigger: MiningTrigger,
+    /// How often the loop checks whether the trigger has fired.
+    pub poll_interval: Duration,
+}
+
+#[derive(Serialize)]
+pub struct AutoMinerStatus {
+    pub running: bool,
+    pub trigger: MiningTrigger,
+    pub blocks_mined: u64,
+    pub hashrate: u64,
+    pub threads: usize,
+    pub last_error: Option<String>,
+}
+
+/// Background loop that mines blocks on its own thread according to a
+/// `MiningTrigger`, until stopped.
+pub struct AutoMiner {
+    config: AutoMineConfig,
+    chain: Arc<Mutex<Chain>>,
+    miner: Arc<Miner>,
+    state: Arc<LoopState>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Default)]
+struct LoopState {
+    running: AtomicBool,
+    blocks_mined: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl AutoMiner {
+    pub fn new(config: AutoMineConfig, chain: Arc<Mutex<Chain>>, miner: Arc<Miner>) -> Self {
+        AutoMiner {
+            config,
+            chain,
+            miner,
+            state: Arc::new(LoopState::default()),
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// Start the loop, returns `false` if it was already running.
+    pub fn start(&self) -> bool {
+        let mut handle = self.handle.lock().unwrap();
+        if self.state.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+
+        let (config, chain, miner, state) = (
+            self.config.clone(),
+            self.chain.clone(),
+            self.miner.clone(),
+            self.state.clone(),
+        );
+        *handle = Some(thread::spawn(move || {
+            AutoMiner::run(config, &chain, &miner, &state)
+        }));
+        true
+    }
+
+    /// Stop the loop, cancelling a block being mined, and wait for it to
+    /// finish. Returns `false` if it was not running.
+    pub fn stop(&self) -> bool {
+        let mut handle = self.handle.lock().unwrap();
+        if !self.state.running.swap(false, Ordering::SeqCst) {
+            return false;
+        }
+        self.miner.cancel();
+        if let Some(handle) = handle.take() {
+            handle.thread().unpark();
+            handle.join().ok();
+        }
+        true
+    }
+
+    pub fn status(&self) -> AutoMinerStatus {
+        AutoMinerStatus {
+            running: self.state.running.load(Ordering::SeqCst),
+            trigger: self.config.trigger,
+            blocks_mined: self.state.blocks_mined.load(Ordering::SeqCst),
+            hashrate: self.miner.hashrate(),
+            threads: self.miner.threads(),
+            last_error: self.state.last_error.lock().unwrap().clone(),
+        }
+    }
+
+    // ---
+    // Private methods
+    // ---
+
+    fn run(config: AutoMineConfig, chain: &Mutex<Chain>, miner: &Miner, state: &LoopState) {
+        let running = || state.running.load(Ordering::SeqCst);
+        let mut last_mined = Instant::now();
+
+        while running() {
+            let (should_mine, allow_empty) = {
+                let chain = chain.lock().unwrap();
+                let pending = chain.current_tx().len();
+
+                match config.trigger {
+                    MiningTrigger::Pending => (pending > 0, false),
+                    MiningTrigger::Interval { secs } => {
+                        (last_mined.elapsed() >= Duration::from_secs(secs), true)
+                    }
+                    MiningTrigger::Threshold { tx_count } => (pending >= tx_count.max(1), false),
+                }
+            };
+
+            if should_mine {
+                match mine_block(chain, miner, allow_empty, running) {
+                    Ok(Some(_)) => {
+                        state.blocks_mined.fetch_add(1, Ordering::SeqCst);
+                        last_mined = Instant::now();
+                        continue;
+                    }
+                    Ok(None) => continue,
+                    // Failing again right away would only spin, so wait a poll
+                    // interval before the next attempt
+                    Err(err) => *state.last_error.lock().unwrap() = Some(err.to_string()),
+                }
+            }
+
+            // Woken early by `stop`
+            thread::park_timeout(config.poll_interval);
+        }
+    }
+}
+
+impl Drop for AutoMiner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{
+        amount::Amount, mempool::MempoolConfig, models::TransactionData, spec::ChainSpec,
+        transaction::TransactionType,
+    };
+    use crate::storage::MemoryStore;
+    use crate::wallet::Wallet;
+
+    fn wallet() -> Wallet {
+        Wallet::from_secret(&[11u8; 32])
+    }
+
+    fn chain() -> Arc<Mutex<Chain>> {
+        let mut spec = ChainSpec::test(
+            wallet().address().to_string(),
+            Amount::from_coins(10).unwrap(),
+        );
+        spec.genesis.difficulty = 1;
+        spec.consensus.retarget_window = 0;
+        spec.consensus.dev_mode = false;
+        let config = spec.config(MempoolConfig::default());
+        let chain = Chain::new(config, wallet().address(), Box::new(MemoryStore::new()));
+        Arc::new(Mutex::new(chain))
+    }
+
+    fn add_tx(chain: &Mutex<Chain>) {
+        let wallet = wallet();
+        let mut chain = chain.lock().unwrap();
+        let tx_data = TransactionData::TransferData {
+            sender: wallet.address().to_string(),
+            receiver: "receiver".to_string(),
+            amount: Amount::from_base_units(1),
+            fee: Amount::ZERO,
+            nonce: chain.next_nonce(wallet.address().as_str()),
+        };
+        let mut tx = Chain::new_transaction(tx_data, TransactionType::Transfer);
+        let signature = wallet.sign(&tx, "test");
+        chain
+            .add_transaction(&mut tx, &wallet.public_key(), &signature)
+            .unwrap();
+    }
+
+    fn auto_miner(chain: &Arc<Mutex<Chain>>, trigger: MiningTrigger) -> AutoMiner {
+        let config = AutoMineConfig {
+            trigger,
+            poll_interval: Duration::from_millis(5),
+        };
+        AutoMiner::new(config, chain.clone(), Arc::new(Miner::new(2)))
+    }
+
+    /// Poll `condition` for up to five seconds.
+    fn wait_for(condition: impl Fn() -> bool) -> bool {
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(5) {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        false
+    }
+
+    #[test]
+    fn mine_pending() {
+        let chain = chain();
+        let auto_miner = auto_miner(&chain, MiningTrigger::Pending);
+
+        assert!(auto_miner.start());
+        assert!(!auto_miner.start());
+
+        add_tx(&chain);
+        assert!(wait_for(|| auto_miner.status().blocks_mined == 1));
+        assert_eq!(chain.lock().unwrap().blocks().len(), 2);
+        assert!(chain.lock().unwrap().current_tx().is_empty());
+
+        let status = auto_miner.status();
+        assert!(status.running);
+        assert!(status.last_error.is_none());
+
+        assert!(auto_miner.stop());
+        assert!(!auto_miner.stop());
+        assert!(!auto_miner.status().running);
+    }
+
+    #[test]
+    fn mine_on_threshold() {
+        let chain = chain();
+        let auto_miner = auto_miner(&chain, MiningTrigger::Threshold { tx_count: 2 });
+        auto_miner.start();
+
+        add_tx(&chain);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(chain.lock().unwrap().blocks().len(), 1);
+
+        add_tx(&chain);
+        assert!(wait_for(|| chain.lock().unwrap().blocks().len() == 2));
+        assert_eq!(chain.lock().unwrap().last_block().tx_count, 3);
+    }
+
+    #[test]
+    fn mine_on_interval() {
+        let chain = chain();
+        let auto_miner = auto_miner(&chain, MiningTrigger::Interval { secs: 0 });
+        auto_miner.start();
+
+        // Blocks are produced without any pending transactions
+        assert!(wait_for(|| chain.lock().unwrap().blocks().len() >= 3));
+        auto_miner.stop();
+        assert!(chain.lock().unwrap().validate().valid);
+    }
+
+    #[test]
+    fn restart() {
+        let chain = chain();
+        let auto_miner = auto_miner(&chain, MiningTrigger::Pending);
+
+        auto_miner.start();
+        auto_miner.stop();
+        assert!(auto_miner.start());
+
+        add_tx(&chain);
+        assert!(wait_for(|| chain.lock().unwrap().blocks().len() == 2));
+    }
+}
@@ -0,0 +1,230 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::blockchain::{block::Block, chain::Chain, error::ChainError};
+
+mod auto;
+mod templates;
+
+pub use auto::{AutoMineConfig, AutoMiner, AutoMinerStatus, MiningTrigger};
+pub use templates::{TemplateCache, MAX_TEMPLATES};
+
+/// Outcome of a mining job, `block` is `None` when the job was cancelled.
+pub struct MiningResult {
+    pub block: Option<Block>,
+    pub hashes: u64,
+    pub elapsed: Duration,
+}
+
+impl MiningResult {
+    /// Hashes per second over the job.
+    pub fn hashrate(&self) -> u64 {
+        let millis = self.elapsed.as_millis().max(1) as u64;
+        self.hashes.saturating_mul(1_000) / millis
+    }
+}
+
+/// Block accepted onto the chain by `mine_block`.
+pub struct MinedBlock {
+    pub block: Block,
+    pub hashrate: u64,
+}
+
+/// Mine a block on top of the tip of `chain` and submit it, only holding the
+/// chain lock to take a template and to submit the solved block.
+///
+/// Returns `None` without mining when there are no pending transactions, unless
+/// `allow_empty` is set, or once `keep_going` says to give up. Otherwise a
+/// cancelled job, or one beaten to the tip, starts over on a fresh template.
+pub fn mine_block(
+    chain: &Mutex<Chain>,
+    miner: &Miner,
+    allow_empty: bool,
+    keep_going: impl Fn() -> bool,
+) -> Result<Option<MinedBlock>, ChainError> {
+    loop {
+        let template = {
+            let chain = chain.lock().unwrap();
+            if chain.current_tx().is_empty() && !allow_empty {
+                return Ok(None);
+            }
+            chain.block_template()
+        };
+        if !keep_going() {
+            return Ok(None);
+        }
+
+        let result = miner.mine(template);
+        let hashrate = result.hashrate();
+        let Some(block) = result.block else {
+            if keep_going() {
+                continue;
+            }
+            return Ok(None);
+        };
+
+        let mut chain = chain.lock().unwrap();
+        match chain.submit_block(block) {
+            Ok(block) => {
+                // Any other job is now working on a stale tip
+                miner.cancel();
+                return Ok(Some(MinedBlock {
+                    block: block.clone(),
+                    hashrate,
+                }));
+            }
+            Err(ChainError::StaleBlock) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Searches nonces for block templates across a number of worker threads.
+///
+/// Mining runs outside the chain lock on a snapshot of the template, the
+/// solved block is then handed back to `Chain::submit_block`. Calling `cancel`
+/// stops every job in progress, e.g. when the tip moves and the templates they
+/// are working on have gone stale.
+pub struct Miner {
+    threads: usize,
+    // Bumped on cancel, jobs stop once it differs from when they started
+    generation: AtomicU64,
+    hashrate: AtomicU64,
+}
+
+impl Miner {
+    pub fn new(threads: usize) -> Self {
+        Miner {
+            threads: threads.max(1),
+            generation: AtomicU64::new(0),
+            hashrate: AtomicU64::new(0),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Hashrate of the most recently finished job, in hashes per second.
+    pub fn hashrate(&self) -> u64 {
+        self.hashrate.load(Ordering::Relaxed)
+    }
+
+    /// Cancel the jobs in progress, if any.
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Search for a nonce that makes the header of `template` meet its target,
+    /// blocking until one is found or the job is cancelled. Worker `i` tries
+    /// nonces `i`, `i + threads`, `i + 2 * threads` and so on.
+    pub fn mine(&self, template: Block) -> MiningResult {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let cancelled = || self.generation.load(Ordering::Relaxed) != generation;
+        let solved = AtomicBool::new(false);
+        let solution = Mutex::new(None);
+        let hashes = AtomicU64::new(0);
+        let started = Instant::now();
+
+        thread::scope(|scope| {
+            for worker in 0..self.threads {
+                let mut header = template.header.clone();
+                let (cancelled, solved, solution, hashes) =
+                    (&cancelled, &solved, &solution, &hashes);
+
+                scope.spawn(move || {
+                    let mut nonce = worker as u64;
+                    let mut count = 0;
+
+                    while !cancelled() && !solved.load(Ordering::Relaxed) {
+                        header.nonce = nonce;
+                        count += 1;
+
+                        if header.meets_target() {
+                            solved.store(true, Ordering::Relaxed);
+                            *solution.lock().unwrap() = Some(nonce);
+                            break;
+                        }
+
+                        nonce = match nonce.checked_add(self.threads as u64) {
+                            Some(next) => next,
+                            None => break,
+                        };
+                    }
+                    hashes.fetch_add(count, Ordering::Relaxed);
+                });
+            }
+        });
+
+        let block = solution.into_inner().unwrap().map(|nonce| {
+            let mut block = template;
+            block.header.nonce = nonce;
+            block
+        });
+        let result = MiningResult {
+            block,
+            hashes: hashes.into_inner(),
+            elapsed: started.elapsed(),
+        };
+        self.hashrate.store(result.hashrate(), Ordering::Relaxed);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{amount::Amount, target::Target};
+    use std::sync::Arc;
+
+    fn template(difficulty: usize) -> Block {
+        Block::new(
+            1,
+            0,
+            Vec::new(),
+            "merkle",
+            "prev_hash",
+            Target::from_difficulty(difficulty),
+            Amount::ZERO,
+        )
+    }
+
+    #[test]
+    fn mine_block() {
+        let miner = Miner::new(4);
+        let result = miner.mine(template(2));
+
+        assert_eq!(miner.hashrate(), result.hashrate());
+        assert!(result.hashes > 0);
+
+        let block = result.block.unwrap();
+        assert!(block.header.meets_target());
+        assert!(block.hash().starts_with("00"));
+    }
+
+    #[test]
+    fn cancel_mining() {
+        let miner = Arc::new(Miner::new(2));
+
+        let handle = {
+            let miner = miner.clone();
+            thread::spawn(move || miner.mine(template(64)))
+        };
+        // Keep cancelling in case the job had not started yet
+        while !handle.is_finished() {
+            miner.cancel();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let result = handle.join().unwrap();
+        assert!(result.block.is_none());
+        assert!(result.hashes > 0);
+    }
+
+    #[test]
+    fn at_least_one_thread() {
+        assert_eq!(Miner::new(0).threads(), 1);
+    }
+}
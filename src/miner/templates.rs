@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use crate::blockchain::block::{Block, BlockHeader};
+
+/// Most templates kept on hand for external miners at a time.
+pub const MAX_TEMPLATES: usize = 64;
+
+/// Block templates handed out to external miners, keyed by merkle root, so a
+/// solved header can be matched back up with the transactions it commits to.
+#[derive(Default)]
+pub struct TemplateCache {
+    templates: HashMap<String, Block>,
+}
+
+impl TemplateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep `template`, dropping templates that no longer build on its parent
+    /// and, past `MAX_TEMPLATES`, the oldest ones.
+    pub fn insert(&mut self, template: Block) {
+        let previous_hash = &template.header.previous_hash;
+        self.templates
+            .retain(|_, cached| &cached.header.previous_hash == previous_hash);
+
+        while self.templates.len() >= MAX_TEMPLATES {
+            let oldest = self
+                .templates
+                .iter()
+                .min_by_key(|(_, cached)| cached.header.timestamp)
+                .map(|(root, _)| root.clone());
+            match oldest {
+                Some(root) => self.templates.remove(&root),
+                None => break,
+            };
+        }
+
+        self.templates
+            .insert(template.header.merkle_root.clone(), template);
+    }
+
+    /// Template `header` was built from, completed with the nonce of `header`.
+    /// Miners only search for the nonce, so a header differing from its
+    /// template in any other field, such as the timestamp, matches none.
+    pub fn complete(&self, header: BlockHeader) -> Option<Block> {
+        let mut block = self.templates.get(&header.merkle_root)?.clone();
+        block.header.nonce = header.nonce;
+        (block.hash() == header.hash()).then_some(block)
+    }
+
+    pub fn clear(&mut self) {
+        self.templates.clear()
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{amount::Amount, target::Target};
+
+    fn template(merkle_root: &str, previous_hash: &str) -> Block {
+        Block::new(
+            1,
+            0,
+            Vec::new(),
+            merkle_root,
+            previous_hash,
+            Target::MAX,
+            Amount::ZERO,
+        )
+    }
+
+    #[test]
+    fn complete_template() {
+        let mut cache = TemplateCache::new();
+        let template = template("root", "tip");
+        cache.insert(template.clone());
+
+        let mut header = template.header;
+        header.nonce = 42;
+        let block = cache.complete(header.clone()).unwrap();
+        assert_eq!(block.header.nonce, 42);
+
+        // Nothing but the nonce may change
+        let mut future = header.clone();
+        future.timestamp += 3600;
+        assert!(cache.complete(future).is_none());
+
+        header.merkle_root = "unknown".to_string();
+        assert!(cache.complete(header).is_none());
+    }
+
+    #[test]
+    fn drop_stale_templates() {
+        let mut cache = TemplateCache::new();
+        cache.insert(template("root_1", "tip"));
+        cache.insert(template("root_2", "tip"));
+        assert_eq!(cache.len(), 2);
+
+        cache.insert(template("root_3", "new_tip"));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.complete(template("root_1", "tip").header).is_none());
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn bounded_size() {
+        let mut cache = TemplateCache::new();
+        for index in 0..MAX_TEMPLATES + 5 {
+            cache.insert(template(&format!("root_{index}"), "tip"));
+        }
+
+        assert_eq!(cache.len(), MAX_TEMPLATES);
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::{
+    block::{Block, BlockHeader},
+    transaction::Transaction,
+};
+
+/// Version of the message protocol, peers speaking another version are refused.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Largest frame accepted from a peer, in bytes.
+pub const MAX_FRAME_SIZE: usize = 32 * 1024 * 1024;
+
+/// Message exchanged between nodes, sent as a big endian `u32` length followed
+/// by that many bytes of bincode.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Message {
+    /// First message sent by both ends of a new connection.
+    Hello {
+        version: u32,
+        /// Hash of the genesis block, nodes only talk to nodes on the same chain.
+        chain_id: String,
+        /// Random id picked at startup, to spot connections back to ourselves
+        /// and a second connection to the same node.
+        node_id: u64,
+        /// Port the sender accepts connections on.
+        listen_port: u16,
+        height: usize,
+    },
+    Transaction(Transaction),
+    Block(Block),
+    /// Ask for the headers following the first block of `locator` the peer
+    /// has on its main chain.
+    GetHeaders {
+        locator: Vec<String>,
+    },
+    /// Headers in chain order, fewer than the most a message holds once the
+    /// sender's tip is reached.
+    Headers(Vec<BlockHeader>),
+    /// Ask for the blocks with the given header hashes.
+    GetBlocks {
+        hashes: Vec<String>,
+    },
+    Blocks(Vec<Block>),
+    GetPeers,
+    /// Addresses other nodes of the network accept connections on.
+    Peers(Vec<SocketAddr>),
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Message) -> io::Result<()> {
+    let payload = bincode::serialize(message).map_err(io::Error::other)?;
+    if payload.len() > MAX_FRAME_SIZE {
+        return Err(frame_too_large(payload.len()));
+    }
+
+    // Written in one go so a frame is not split over several small packets
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+pub fn read_message(reader: &mut impl Read) -> io::Result<Message> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(frame_too_large(len));
+    }
+
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    bincode::deserialize(&payload).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn frame_too_large(len: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("frame of {len} bytes exceeds the limit of {MAX_FRAME_SIZE}"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trip() {
+        let mut buf = Vec::new();
+        let locator = vec!["tip".to_string(), "genesis".to_string()];
+        write_message(&mut buf, &Message::GetHeaders { locator }).unwrap();
+        write_message(&mut buf, &Message::GetPeers).unwrap();
+
+        let mut reader = Cursor::new(buf);
+        assert!(matches!(
+            read_message(&mut reader).unwrap(),
+            Message::GetHeaders { locator } if locator == ["tip", "genesis"]
+        ));
+        assert!(matches!(
+            read_message(&mut reader).unwrap(),
+            Message::GetPeers
+        ));
+
+        let err = read_message(&mut reader).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reject_bad_frames() {
+        let oversized = ((MAX_FRAME_SIZE + 1) as u32).to_be_bytes();
+        let err = read_message(&mut Cursor::new(oversized)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let garbage = [0, 0, 0, 2, 0xff, 0xff];
+        let err = read_message(&mut Cursor::new(garbage)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
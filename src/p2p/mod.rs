@@ -0,0 +1,14 @@
+//! Peer to peer networking between nodes over TCP.
+//!
+//! Nodes handshake with their protocol version, chain id and height, then
+//! announce the transactions and blocks added to their chain to each other and
+//! share the addresses of their peers. A node behind its peers downloads and
+//! checks the headers it is missing before fetching their blocks.
+
+mod message;
+mod node;
+mod sync;
+
+pub use message::{read_message, write_message, Message, MAX_FRAME_SIZE, PROTOCOL_VERSION};
+pub use node::{Node, NodeConfig, PeerError, PeerInfo};
+pub use sync::{BlockSync, SyncStatus, MAX_BLOCKS_PER_MESSAGE, MAX_HEADERS_PER_MESSAGE};
@@ -0,0 +1,677 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufReader};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand_core::{OsRng, RngCore};
+use serde::Serialize;
+
+use super::message::{read_message, write_message, Message, PROTOCOL_VERSION};
+use super::sync::{BlockSync, SyncStatus, MAX_BLOCKS_PER_MESSAGE, MAX_HEADERS_PER_MESSAGE};
+use crate::blockchain::{
+    block::{Block, BlockHeader},
+    chain::{Chain, ChainEvent},
+    error::ChainError,
+    validation::validate_headers,
+};
+
+const DEFAULT_MAX_PEERS: usize = 8;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most addresses taken from a single `Peers` message.
+const MAX_PEER_ADDRS: usize = 32;
+/// How long a peer may leave a message unread before it is disconnected.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often blocks that were asked for and never arrived are asked for again.
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+pub struct NodeConfig {
+    /// Address to accept peer connections on, port 0 picks a free port.
+    pub listen_addr: SocketAddr,
+    /// Peers to connect to on startup.
+    pub seeds: Vec<SocketAddr>,
+    pub max_peers: usize,
+}
+
+impl NodeConfig {
+    pub fn new(listen_addr: SocketAddr) -> Self {
+        NodeConfig {
+            listen_addr,
+            seeds: Vec::new(),
+            max_peers: DEFAULT_MAX_PEERS,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PeerInfo {
+    /// Address the peer accepts connections on.
+    pub addr: SocketAddr,
+    /// Height of the peer's chain, as far as we know.
+    pub height: usize,
+    /// Whether we opened the connection.
+    pub outbound: bool,
+}
+
+#[derive(Debug)]
+pub enum PeerError {
+    Io(io::Error),
+    VersionMismatch { ours: u32, theirs: u32 },
+    ChainMismatch { ours: String, theirs: String },
+    SelfConnection,
+    AlreadyConnected,
+    TooManyPeers,
+    Stopped,
+    UnexpectedMessage,
+}
+
+impl fmt::Display for PeerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "connection failed: {err}"),
+            Self::VersionMismatch { ours, theirs } => {
+                write!(f, "peer speaks protocol version {theirs}, not {ours}")
+            }
+            Self::ChainMismatch { ours, theirs } => {
+                write!(f, "peer is on chain {theirs}, not {ours}")
+            }
+            Self::SelfConnection => write!(f, "connected to ourselves"),
+            Self::AlreadyConnected => write!(f, "already connected to peer"),
+            Self::TooManyPeers => write!(f, "peer limit reached"),
+            Self::Stopped => write!(f, "node is shut down"),
+            Self::UnexpectedMessage => write!(f, "peer did not start with a handshake"),
+        }
+    }
+}
+
+impl std::error::Error for PeerError {}
+
+impl From<io::Error> for PeerError {
+    fn from(err: io::Error) -> Self {
+        PeerError::Io(err)
+    }
+}
+
+/// Node of the peer to peer network, sharing the transactions and blocks added
+/// to its chain with its peers and adding theirs.
+///
+/// Every connection gets a thread reading the peer's messages, while one more
+/// thread relays the events of the chain to all peers. Nodes that already hold
+/// a transaction or block reject it, so relayed items stop spreading once every
+/// node has them.
+///
+/// A node behind a peer catches up headers first, see [`BlockSync`].
+pub struct Node {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    config: NodeConfig,
+    chain: Arc<Mutex<Chain>>,
+    chain_id: String,
+    node_id: u64,
+    local_addr: SocketAddr,
+    peers: Mutex<HashMap<u64, Arc<Peer>>>,
+    // Addresses being connected to after hearing of them, locked after the peers
+    connecting: Mutex<HashSet<SocketAddr>>,
+    // Locked before the chain, which is locked before the peers
+    sync: Mutex<BlockSync>,
+    next_connection: AtomicU64,
+    stopped: AtomicBool,
+}
+
+struct Peer {
+    // Tells a connection apart from an earlier one to the same node
+    connection: u64,
+    node_id: u64,
+    addr: SocketAddr,
+    outbound: bool,
+    height: AtomicUsize,
+    writer: Mutex<TcpStream>,
+    // Shuts the connection down without waiting on a write in progress
+    socket: TcpStream,
+}
+
+impl Node {
+    /// Listen on the configured address, start relaying the events of `chain`
+    /// and connect to the seeds in the background.
+    pub fn start(config: NodeConfig, chain: Arc<Mutex<Chain>>) -> io::Result<Node> {
+        let listener = TcpListener::bind(config.listen_addr)?;
+        let (chain_id, events) = {
+            let mut chain = chain.lock().unwrap();
+            let genesis = chain
+                .get_block(0)
+                .expect("Chain always has a genesis block");
+            (genesis.hash(), chain.subscribe())
+        };
+
+        let shared = Arc::new(Shared {
+            local_addr: listener.local_addr()?,
+            config,
+            chain,
+            chain_id,
+            node_id: OsRng.next_u64(),
+            peers: Mutex::new(HashMap::new()),
+            connecting: Mutex::new(HashSet::new()),
+            sync: Mutex::new(BlockSync::new()),
+            next_connection: AtomicU64::new(0),
+            stopped: AtomicBool::new(false),
+        });
+
+        let accepting = shared.clone();
+        thread::spawn(move || accepting.accept(listener));
+        let relaying = shared.clone();
+        thread::spawn(move || relaying.relay(events));
+        let syncing = shared.clone();
+        thread::spawn(move || syncing.sync());
+
+        // Unreachable seeds are skipped, other peers can still be found
+        for seed in shared.config.seeds.clone() {
+            let connecting = shared.clone();
+            thread::spawn(move || connecting.connect(seed).ok());
+        }
+
+        Ok(Node { shared })
+    }
+
+    /// Connect to the node listening on `addr` and handshake with it.
+    pub fn connect(&self, addr: SocketAddr) -> Result<PeerInfo, PeerError> {
+        self.shared.connect(addr)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.shared.local_addr
+    }
+
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        let peers = self.shared.peers.lock().unwrap();
+        let mut peers: Vec<PeerInfo> = peers.values().map(|peer| peer.info()).collect();
+        peers.sort_by_key(|peer| peer.addr);
+        peers
+    }
+
+    pub fn sync_status(&self) -> SyncStatus {
+        let sync = self.shared.sync.lock().unwrap();
+        let height = self.shared.chain.lock().unwrap().height();
+        let best_height = self
+            .shared
+            .peer_heights()
+            .into_iter()
+            .map(|(_, height)| height)
+            .fold(height, usize::max);
+        sync.status(height, best_height)
+    }
+
+    /// Stop accepting connections and disconnect from every peer.
+    pub fn shutdown(&self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+
+        // Wake the accept loop so it sees the node has stopped
+        TcpStream::connect(self.shared.local_addr).ok();
+
+        for (_, peer) in self.shared.peers.lock().unwrap().drain() {
+            peer.close();
+        }
+    }
+}
+
+impl Shared {
+    fn accept(self: Arc<Self>, listener: TcpListener) {
+        for stream in listener.incoming() {
+            if self.stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(stream) = stream else { continue };
+
+            let shared = self.clone();
+            thread::spawn(move || shared.add_peer(stream, false).ok());
+        }
+    }
+
+    fn connect(self: &Arc<Self>, addr: SocketAddr) -> Result<PeerInfo, PeerError> {
+        let stream = TcpStream::connect_timeout(&addr, HANDSHAKE_TIMEOUT)?;
+        self.add_peer(stream, true)
+    }
+
+    /// Handshake over a new connection, then keep reading the peer's messages
+    /// on a thread of its own.
+    fn add_peer(
+        self: &Arc<Self>,
+        stream: TcpStream,
+        outbound: bool,
+    ) -> Result<PeerInfo, PeerError> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let remote = stream.peer_addr()?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        let height = self.chain.lock().unwrap().height();
+        let hello = Message::Hello {
+            version: PROTOCOL_VERSION,
+            chain_id: self.chain_id.clone(),
+            node_id: self.node_id,
+            listen_port: self.local_addr.port(),
+            height,
+        };
+        write_message(&mut writer, &hello)?;
+
+        let Message::Hello {
+            version,
+            chain_id,
+            node_id,
+            listen_port,
+            height: peer_height,
+        } = read_message(&mut reader)?
+        else {
+            return Err(PeerError::UnexpectedMessage);
+        };
+        if version != PROTOCOL_VERSION {
+            return Err(PeerError::VersionMismatch {
+                ours: PROTOCOL_VERSION,
+                theirs: version,
+            });
+        }
+        if chain_id != self.chain_id {
+            return Err(PeerError::ChainMismatch {
+                ours: self.chain_id.clone(),
+                theirs: chain_id,
+            });
+        }
+        if node_id == self.node_id {
+            return Err(PeerError::SelfConnection);
+        }
+        reader.get_ref().set_read_timeout(None)?;
+
+        let peer = Arc::new(Peer {
+            connection: self.next_connection.fetch_add(1, Ordering::SeqCst),
+            node_id,
+            addr: SocketAddr::new(remote.ip(), listen_port),
+            outbound,
+            height: AtomicUsize::new(peer_height),
+            socket: writer.try_clone()?,
+            writer: Mutex::new(writer),
+        });
+        self.register(&peer)?;
+
+        let shared = self.clone();
+        let reading = peer.clone();
+        thread::spawn(move || shared.read_loop(&reading, reader));
+
+        peer.send(&Message::GetPeers)?;
+        if peer_height > height {
+            self.start_sync(&peer)?;
+        }
+        Ok(peer.info())
+    }
+
+    fn register(&self, peer: &Arc<Peer>) -> Result<(), PeerError> {
+        let mut peers = self.peers.lock().unwrap();
+        if self.stopped.load(Ordering::SeqCst) {
+            return Err(PeerError::Stopped);
+        }
+
+        if let Some(existing) = peers.get(&peer.node_id) {
+            // When two nodes connect to each other at the same time, both keep
+            // the connection opened by the node with the lower id
+            if self.opener(existing) <= self.opener(peer) {
+                return Err(PeerError::AlreadyConnected);
+            }
+            existing.close();
+        } else if peers.len() >= self.config.max_peers {
+            return Err(PeerError::TooManyPeers);
+        }
+
+        peers.insert(peer.node_id, peer.clone());
+        Ok(())
+    }
+
+    fn opener(&self, peer: &Peer) -> u64 {
+        if peer.outbound {
+            self.node_id
+        } else {
+            peer.node_id
+        }
+    }
+
+    fn read_loop(self: &Arc<Self>, peer: &Peer, mut reader: BufReader<TcpStream>) {
+        while let Ok(message) = read_message(&mut reader) {
+            if self.handle(peer, message).is_err() {
+                break;
+            }
+        }
+
+        peer.close();
+        let removed = {
+            let mut peers = self.peers.lock().unwrap();
+            let current = peers
+                .get(&peer.node_id)
+                .is_some_and(|current| current.connection == peer.connection);
+            current && peers.remove(&peer.node_id).is_some()
+        };
+        if removed {
+            self.remove_from_sync(peer);
+        }
+    }
+
+    fn handle(self: &Arc<Self>, peer: &Peer, message: Message) -> io::Result<()> {
+        match message {
+            Message::Hello { .. } => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "handshake repeated",
+            )),
+            Message::Transaction(mut tx) => {
+                let (Some(public_key), Some(signature)) =
+                    (tx.public_key.clone(), tx.signature.clone())
+                else {
+                    return Ok(());
+                };
+
+                // Known and invalid transactions go no further
+                let mut chain = self.chain.lock().unwrap();
+                chain.add_transaction(&mut tx, &public_key, &signature).ok();
+                Ok(())
+            }
+            Message::Block(block) => {
+                let height = block.header.index.saturating_add(1);
+                let result = self.chain.lock().unwrap().submit_block(block).map(|_| ());
+                // Only a block that checks out tells how high the peer is
+                if matches!(result, Ok(()) | Err(ChainError::KnownBlock)) {
+                    peer.saw_height(height);
+                }
+                let unknown_parent = matches!(result, Err(ChainError::UnknownParent));
+                // We are behind, or the peer is on a branch forking off further back
+                if unknown_parent {
+                    self.start_sync(peer)?;
+                }
+                Ok(())
+            }
+            Message::GetHeaders { locator } => {
+                let headers = self
+                    .chain
+                    .lock()
+                    .unwrap()
+                    .headers_after(&locator, MAX_HEADERS_PER_MESSAGE);
+                peer.send(&Message::Headers(headers))
+            }
+            Message::Headers(headers) => self.add_headers(peer, headers),
+            Message::GetBlocks { hashes } => {
+                let blocks = {
+                    let chain = self.chain.lock().unwrap();
+                    hashes
+                        .iter()
+                        .take(MAX_BLOCKS_PER_MESSAGE)
+                        .filter_map(|hash| chain.known_block(hash).cloned())
+                        .collect()
+                };
+                peer.send(&Message::Blocks(blocks))
+            }
+            Message::Blocks(blocks) => {
+                self.add_blocks(blocks);
+                Ok(())
+            }
+            Message::GetPeers => {
+                let addrs = {
+                    let peers = self.peers.lock().unwrap();
+                    peers
+                        .values()
+                        .filter(|other| other.node_id != peer.node_id)
+                        .map(|other| other.addr)
+                        .collect()
+                };
+                peer.send(&Message::Peers(addrs))
+            }
+            Message::Peers(addrs) => {
+                for addr in addrs.into_iter().take(MAX_PEER_ADDRS) {
+                    if self.start_connecting(addr) {
+                        let shared = self.clone();
+                        thread::spawn(move || {
+                            shared.connect(addr).ok();
+                            shared.connecting.lock().unwrap().remove(&addr);
+                        });
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Download headers from `peer`, following on from the last header
+    /// downloaded or our tip, unless already downloading them from another peer.
+    fn start_sync(&self, peer: &Peer) -> io::Result<()> {
+        let locator = {
+            let mut sync = self.sync.lock().unwrap();
+            if !sync.start(peer.node_id, Instant::now()) {
+                return Ok(());
+            }
+            let mut locator = self.chain.lock().unwrap().locator();
+            if let Some(last) = sync.last_header() {
+                locator.insert(0, last.hash());
+            }
+            locator
+        };
+        peer.send(&Message::GetHeaders { locator })
+    }
+
+    /// Check `headers` from the header peer form a chain following on from the
+    /// last header downloaded or a block we have, then download their blocks.
+    /// A peer sending headers that do not is disconnected.
+    fn add_headers(&self, peer: &Peer, headers: Vec<BlockHeader>) -> io::Result<()> {
+        let next = {
+            let mut sync = self.sync.lock().unwrap();
+            if sync.header_peer() != Some(peer.node_id) {
+                return Ok(());
+            }
+
+            let chain = self.chain.lock().unwrap();
+            if let Some(first) = headers.first() {
+                let parent = match sync.last_header() {
+                    Some(last) if last.hash() == first.previous_hash => Some(last.clone()),
+                    _ => chain
+                        .known_block(&first.previous_hash)
+                        .map(|parent| parent.header.clone()),
+                };
+                let Some(parent) = parent else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "headers do not connect to our chain",
+                    ));
+                };
+                validate_headers(&parent, &headers, chain.easiest_target())
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                peer.saw_height(parent.index + headers.len() + 1);
+            }
+
+            let more = sync.add_headers(
+                headers,
+                |hash| chain.known_block(hash).is_some(),
+                Instant::now(),
+            );
+            sync.last_header()
+                .filter(|_| more)
+                .map(|last| vec![last.hash()])
+        };
+
+        if let Some(locator) = next {
+            peer.send(&Message::GetHeaders { locator })?;
+        }
+        self.request_blocks();
+        Ok(())
+    }
+
+    /// Add the downloaded blocks next in line to the chain, and ask for more.
+    fn add_blocks(&self, blocks: Vec<Block>) {
+        {
+            let mut sync = self.sync.lock().unwrap();
+            let ready = sync.add_blocks(blocks);
+            let mut chain = self.chain.lock().unwrap();
+            for block in ready {
+                match chain.submit_block(block) {
+                    Ok(_) | Err(ChainError::StaleBlock | ChainError::KnownBlock) => {}
+                    // The headers held up but the block does not, so whatever
+                    // follows it is no good either
+                    Err(_) => {
+                        sync.reset();
+                        break;
+                    }
+                }
+            }
+        }
+        self.request_blocks();
+    }
+
+    /// Ask peers for the next batches of blocks to download.
+    fn request_blocks(&self) {
+        let requests = self
+            .sync
+            .lock()
+            .unwrap()
+            .next_requests(&self.peer_heights(), Instant::now());
+
+        for (node_id, hashes) in requests {
+            let peer = self.peers.lock().unwrap().get(&node_id).cloned();
+            if let Some(peer) = peer {
+                peer.send(&Message::GetBlocks { hashes }).ok();
+            }
+        }
+    }
+
+    /// Hand what was asked of a disconnected `peer` to the others, and carry on
+    /// downloading headers from the highest peer if it was sending them.
+    fn remove_from_sync(&self, peer: &Peer) {
+        if !self.sync.lock().unwrap().remove_peer(peer.node_id) {
+            return;
+        }
+
+        let height = self.chain.lock().unwrap().height();
+        let highest = {
+            let peers = self.peers.lock().unwrap();
+            peers.values().max_by_key(|peer| peer.height()).cloned()
+        };
+        if let Some(highest) = highest.filter(|highest| highest.height() > height) {
+            self.start_sync(&highest).ok();
+        }
+        self.request_blocks();
+    }
+
+    /// Periodically ask again for headers and blocks that never arrived.
+    fn sync(&self) {
+        while !self.stopped.load(Ordering::SeqCst) {
+            thread::sleep(SYNC_INTERVAL);
+            self.retry_headers();
+            self.request_blocks();
+        }
+    }
+
+    /// Carry on downloading headers from the highest peer, another one if
+    /// there is, once the header peer stops answering. With no peer ahead of
+    /// us any more, there are no headers left to download.
+    fn retry_headers(&self) {
+        let stalled = self
+            .sync
+            .lock()
+            .unwrap()
+            .stalled_header_peer(Instant::now());
+        let Some(stalled) = stalled else {
+            return;
+        };
+
+        let height = self.chain.lock().unwrap().height();
+        let next = {
+            let peers = self.peers.lock().unwrap();
+            peers
+                .values()
+                .filter(|peer| peer.height() > height)
+                .max_by_key(|peer| (peer.node_id != stalled, peer.height()))
+                .cloned()
+        };
+        match next {
+            Some(next) => {
+                self.start_sync(&next).ok();
+            }
+            None => self.sync.lock().unwrap().stop_headers(),
+        }
+    }
+
+    fn peer_heights(&self) -> Vec<(u64, usize)> {
+        let peers = self.peers.lock().unwrap();
+        peers
+            .values()
+            .map(|peer| (peer.node_id, peer.height()))
+            .collect()
+    }
+
+    fn relay(&self, events: Receiver<ChainEvent>) {
+        for event in events {
+            if self.stopped.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let message = match event {
+                ChainEvent::Transaction(tx) => Message::Transaction(tx),
+                // Blocks added while catching up are old news to our peers
+                ChainEvent::Block(_) if self.sync.lock().unwrap().is_syncing() => continue,
+                ChainEvent::Block(block) => Message::Block(block),
+            };
+            self.broadcast(&message);
+        }
+    }
+
+    fn broadcast(&self, message: &Message) {
+        // Sent outside the lock, and a peer that stops reading is dropped once
+        // a write times out, so it holds up the others for a while at most
+        let peers: Vec<Arc<Peer>> = self.peers.lock().unwrap().values().cloned().collect();
+        for peer in peers {
+            peer.send(message).ok();
+        }
+    }
+
+    /// Whether to connect to `addr`, which is then counted against the peer
+    /// limit until the connection attempt is over.
+    fn start_connecting(&self, addr: SocketAddr) -> bool {
+        let peers = self.peers.lock().unwrap();
+        let mut connecting = self.connecting.lock().unwrap();
+        addr != self.local_addr
+            && peers.len() + connecting.len() < self.config.max_peers
+            && peers.values().all(|peer| peer.addr != addr)
+            && connecting.insert(addr)
+    }
+}
+
+impl Peer {
+    fn info(&self) -> PeerInfo {
+        PeerInfo {
+            addr: self.addr,
+            height: self.height(),
+            outbound: self.outbound,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.height.load(Ordering::SeqCst)
+    }
+
+    fn saw_height(&self, height: usize) {
+        self.height.fetch_max(height, Ordering::SeqCst);
+    }
+
+    /// Send `message`, closing the connection if it fails.
+    fn send(&self, message: &Message) -> io::Result<()> {
+        let result = write_message(&mut *self.writer.lock().unwrap(), message);
+        if result.is_err() {
+            self.close();
+        }
+        result
+    }
+
+    /// Close the connection, which ends its read loop.
+    fn close(&self) {
+        self.socket.shutdown(Shutdown::Both).ok();
+    }
+}
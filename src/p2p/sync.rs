@@ -0,0 +1,384 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::blockchain::block::{Block, BlockHeader};
+
+/// Most headers sent in reply to a single `GetHeaders`.
+pub const MAX_HEADERS_PER_MESSAGE: usize = 500;
+/// Most blocks sent in reply to a single `GetBlocks`.
+pub const MAX_BLOCKS_PER_MESSAGE: usize = 50;
+/// Most blocks asked for and not received yet, across every peer.
+const MAX_BLOCKS_IN_FLIGHT: usize = 8 * MAX_BLOCKS_PER_MESSAGE;
+/// Blocks and headers asked for longer ago than this are asked for again,
+/// headers of another peer if there is one.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How far the node is in catching up with its peers.
+#[derive(Clone, Debug, Serialize)]
+pub struct SyncStatus {
+    pub syncing: bool,
+    /// Number of blocks in our chain.
+    pub height: usize,
+    /// Highest height announced by a peer, or ours if higher.
+    pub best_height: usize,
+    /// Height reached by the headers downloaded so far.
+    pub header_height: usize,
+    /// Blocks asked for and not received yet.
+    pub blocks_in_flight: usize,
+}
+
+/// Download of the blocks our chain is missing, headers first.
+///
+/// Headers come from a single peer and are checked as a chain before any of
+/// their blocks is asked for. Blocks are then asked for in batches, spread over
+/// every peer known to have them, and handed back in chain order as they
+/// arrive. Talking to peers is left to the caller.
+#[derive(Default)]
+pub struct BlockSync {
+    // Peer the headers are downloaded from, and when they were last asked for
+    header_peer: Option<(u64, Instant)>,
+    // Last header downloaded, the next ones follow on from it
+    last_header: Option<BlockHeader>,
+    // Blocks still to add to the chain, in order, by hash and index
+    wanted: VecDeque<(String, usize)>,
+    // Blocks asked for by hash, with the peer asked and when
+    requested: HashMap<String, (u64, Instant)>,
+    // Blocks that arrived before the blocks they follow
+    received: HashMap<String, Block>,
+}
+
+impl BlockSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.header_peer.is_some() || !self.wanted.is_empty()
+    }
+
+    pub fn header_peer(&self) -> Option<u64> {
+        self.header_peer.map(|(peer, _)| peer)
+    }
+
+    /// Header peer that has not answered within `REQUEST_TIMEOUT` of being
+    /// asked, whose download can be handed to another peer.
+    pub fn stalled_header_peer(&self, now: Instant) -> Option<u64> {
+        self.header_peer
+            .filter(|(_, asked)| now.duration_since(*asked) >= REQUEST_TIMEOUT)
+            .map(|(peer, _)| peer)
+    }
+
+    pub fn last_header(&self) -> Option<&BlockHeader> {
+        self.last_header.as_ref()
+    }
+
+    /// Download headers from `peer`, asking for them at `now`, unless already
+    /// downloading them from a peer that has not stalled.
+    pub fn start(&mut self, peer: u64, now: Instant) -> bool {
+        if self.header_peer.is_some() && self.stalled_header_peer(now).is_none() {
+            return false;
+        }
+        self.header_peer = Some((peer, now));
+        true
+    }
+
+    /// Queue the blocks of `headers`, already checked to follow on from the
+    /// last header or a block we have, leaving out the blocks `is_known`.
+    ///
+    /// Returns whether the header peer may have more headers to send, in which
+    /// case they are taken to be asked for at `now`.
+    pub fn add_headers(
+        &mut self,
+        headers: Vec<BlockHeader>,
+        is_known: impl Fn(&str) -> bool,
+        now: Instant,
+    ) -> bool {
+        let more = headers.len() >= MAX_HEADERS_PER_MESSAGE;
+        for header in headers {
+            let hash = header.hash();
+            if !is_known(&hash) {
+                self.wanted.push_back((hash, header.index));
+            }
+            self.last_header = Some(header);
+        }
+
+        if more {
+            if let Some((_, asked)) = &mut self.header_peer {
+                *asked = now;
+            }
+        } else {
+            self.header_peer = None;
+            self.forget_finished();
+        }
+        more
+    }
+
+    /// Batches of blocks to ask for next, each from one of `peers`, given by
+    /// id and height, that has all of them. Peers take turns so the batches
+    /// download in parallel.
+    pub fn next_requests(
+        &mut self,
+        peers: &[(u64, usize)],
+        now: Instant,
+    ) -> Vec<(u64, Vec<String>)> {
+        self.requested
+            .retain(|_, (_, asked)| now.duration_since(*asked) < REQUEST_TIMEOUT);
+
+        // Batches with the index of their last block
+        let mut batches = Vec::new();
+        let mut batch = Vec::new();
+        let mut last = 0;
+        let mut budget = MAX_BLOCKS_IN_FLIGHT.saturating_sub(self.requested.len());
+        for (hash, index) in &self.wanted {
+            if budget == 0 {
+                break;
+            }
+            if self.requested.contains_key(hash) || self.received.contains_key(hash) {
+                continue;
+            }
+
+            batch.push(hash.clone());
+            last = *index;
+            budget -= 1;
+            if batch.len() == MAX_BLOCKS_PER_MESSAGE {
+                batches.push((mem::take(&mut batch), last));
+            }
+        }
+        if !batch.is_empty() {
+            batches.push((batch, last));
+        }
+
+        let mut requests = Vec::new();
+        for (turn, (hashes, last)) in batches.into_iter().enumerate() {
+            let holders: Vec<u64> = peers
+                .iter()
+                .filter(|(_, height)| *height > last)
+                .map(|(peer, _)| *peer)
+                .collect();
+            if holders.is_empty() {
+                continue;
+            }
+
+            let peer = holders[turn % holders.len()];
+            for hash in &hashes {
+                self.requested.insert(hash.clone(), (peer, now));
+            }
+            requests.push((peer, hashes));
+        }
+        requests
+    }
+
+    /// Keep the `blocks` that were asked for, and return the ones now next in
+    /// line for the chain, in order.
+    pub fn add_blocks(&mut self, blocks: Vec<Block>) -> Vec<Block> {
+        for block in blocks {
+            let hash = block.hash();
+            if self.requested.remove(&hash).is_some() {
+                self.received.insert(hash, block);
+            }
+        }
+
+        let mut ready = Vec::new();
+        while let Some(block) = self
+            .wanted
+            .front()
+            .and_then(|(hash, _)| self.received.remove(hash))
+        {
+            self.wanted.pop_front();
+            ready.push(block);
+        }
+        self.forget_finished();
+        ready
+    }
+
+    /// Forget what was asked of a disconnected `peer`, so it can be asked of
+    /// others. Returns whether headers were being downloaded from it.
+    pub fn remove_peer(&mut self, peer: u64) -> bool {
+        self.requested.retain(|_, (asked, _)| *asked != peer);
+        if self.header_peer() != Some(peer) {
+            return false;
+        }
+        self.header_peer = None;
+        self.forget_finished();
+        true
+    }
+
+    /// Stop downloading headers, once no peer is ahead of us any more.
+    pub fn stop_headers(&mut self) {
+        self.header_peer = None;
+        self.forget_finished();
+    }
+
+    /// Drop everything left to download, once a block turned out invalid.
+    pub fn reset(&mut self) {
+        *self = BlockSync::default();
+    }
+
+    pub fn status(&self, height: usize, best_height: usize) -> SyncStatus {
+        let header_height = self
+            .last_header
+            .as_ref()
+            .map_or(height, |header| height.max(header.index + 1));
+
+        SyncStatus {
+            syncing: self.is_syncing(),
+            height,
+            best_height: best_height.max(height),
+            header_height,
+            blocks_in_flight: self.requested.len(),
+        }
+    }
+
+    // ---
+    // Private methods
+    // ---
+
+    fn forget_finished(&mut self) {
+        if !self.is_syncing() {
+            self.last_header = None;
+            self.received.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{amount::Amount, target::Target};
+
+    /// Chain of `count` blocks after a genesis block.
+    fn blocks(count: usize) -> Vec<Block> {
+        let mut blocks = vec![Block::new(
+            0,
+            0,
+            Vec::new(),
+            "merkle",
+            "genesis",
+            Target::MAX,
+            Amount::ZERO,
+        )];
+        for index in 1..=count {
+            let previous_hash = blocks[index - 1].hash();
+            blocks.push(Block::new(
+                index,
+                0,
+                Vec::new(),
+                "merkle",
+                &previous_hash,
+                Target::MAX,
+                Amount::ZERO,
+            ));
+        }
+        blocks
+    }
+
+    fn indexes(blocks: &[Block]) -> Vec<usize> {
+        blocks.iter().map(|block| block.header.index).collect()
+    }
+
+    #[test]
+    fn download_in_batches() {
+        let blocks = blocks(120);
+        let headers = blocks.iter().map(|block| block.header.clone()).collect();
+        let genesis = blocks[0].hash();
+
+        let now = Instant::now();
+        let mut sync = BlockSync::new();
+        assert!(sync.start(1, now));
+        assert!(!sync.start(2, now));
+        assert!(!sync.add_headers(headers, |hash| hash == genesis, now));
+        assert_eq!(sync.header_peer(), None);
+        assert!(sync.is_syncing());
+
+        // Peer 3 is only halfway, so only gets a turn at the first batch
+        let peers = [(1, 121), (2, 121), (3, 60)];
+        let requests = sync.next_requests(&peers, now);
+        let asked: Vec<(u64, usize)> = requests
+            .iter()
+            .map(|(peer, hashes)| (*peer, hashes.len()))
+            .collect();
+        assert_eq!(asked, [(1, 50), (2, 50), (1, 20)]);
+        assert_eq!(requests[0].1[0], blocks[1].hash());
+        assert!(sync.next_requests(&peers, now).is_empty());
+        assert_eq!(sync.status(1, 121).blocks_in_flight, 120);
+        assert_eq!(sync.status(1, 121).header_height, 121);
+
+        // Blocks come out in chain order, whatever order they arrive in
+        assert!(sync.add_blocks(blocks[51..=100].to_vec()).is_empty());
+        let ready = sync.add_blocks(blocks[1..=50].to_vec());
+        assert_eq!(indexes(&ready), (1..=100).collect::<Vec<_>>());
+
+        // Whatever a lost peer was asked goes to another
+        sync.remove_peer(1);
+        let requests = sync.next_requests(&[(2, 121)], now);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0, 2);
+        assert_eq!(requests[0].1.len(), 20);
+
+        // Blocks that were not asked for are ignored
+        assert!(sync.add_blocks(vec![blocks[1].clone()]).is_empty());
+        let ready = sync.add_blocks(blocks[101..].to_vec());
+        assert_eq!(indexes(&ready), (101..=120).collect::<Vec<_>>());
+        assert!(!sync.is_syncing());
+        assert!(sync.last_header().is_none());
+    }
+
+    #[test]
+    fn ask_again_after_timeout() {
+        let blocks = blocks(3);
+        let headers = blocks[1..]
+            .iter()
+            .map(|block| block.header.clone())
+            .collect();
+
+        let now = Instant::now();
+        let mut sync = BlockSync::new();
+        sync.start(1, now);
+        sync.add_headers(headers, |_| false, now);
+
+        assert_eq!(sync.next_requests(&[(1, 4)], now)[0].0, 1);
+        assert!(sync.next_requests(&[(1, 4), (2, 4)], now).is_empty());
+        let later = now + REQUEST_TIMEOUT;
+        assert_eq!(sync.next_requests(&[(2, 4)], later)[0].1.len(), 3);
+    }
+
+    #[test]
+    fn resume_headers() {
+        // A full message of headers means the peer may have more
+        let now = Instant::now();
+        let mut sync = BlockSync::new();
+        sync.start(1, now);
+        let headers = blocks(MAX_HEADERS_PER_MESSAGE)[1..]
+            .iter()
+            .map(|block| block.header.clone())
+            .collect();
+        let later = now + REQUEST_TIMEOUT / 2;
+        assert!(sync.add_headers(headers, |_| false, later));
+        assert_eq!(sync.header_peer(), Some(1));
+
+        // Until the header peer stalls, the download stays with it
+        let timeout = later + REQUEST_TIMEOUT;
+        assert_eq!(
+            sync.stalled_header_peer(timeout - Duration::from_secs(1)),
+            None
+        );
+        assert!(!sync.start(2, timeout - Duration::from_secs(1)));
+        assert_eq!(sync.stalled_header_peer(timeout), Some(1));
+        assert!(sync.start(2, timeout));
+        assert_eq!(sync.header_peer(), Some(2));
+
+        // Another peer can carry on from the last header
+        assert!(sync.remove_peer(2));
+        assert_eq!(sync.last_header().unwrap().index, MAX_HEADERS_PER_MESSAGE);
+        assert!(sync.start(3, timeout));
+        sync.stop_headers();
+        assert_eq!(sync.header_peer(), None);
+        assert_eq!(sync.status(1, 1).header_height, MAX_HEADERS_PER_MESSAGE + 1);
+
+        sync.reset();
+        assert!(!sync.is_syncing());
+    }
+}
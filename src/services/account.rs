@@ -0,0 +1,90 @@
+use actix_web::{
+    get,
+    web::{scope, Data, Path},
+    HttpResponse, Scope,
+};
+use serde::Serialize;
+
+use crate::{
+    app::AppState,
+    blockchain::{amount::Amount, transaction::Transaction},
+    services::error_response,
+    wallet::Address,
+};
+
+#[derive(Serialize)]
+pub struct BalanceResponse {
+    address: Address,
+    balance: Amount,
+}
+
+#[derive(Serialize)]
+pub struct NonceResponse {
+    address: Address,
+    nonce: u64,
+}
+
+#[derive(Serialize)]
+pub struct AccountTransaction<'a> {
+    block_index: usize,
+    #[serde(flatten)]
+    transaction: &'a Transaction,
+}
+
+#[get("/{address}/balance")]
+async fn get_balance(address: Path<String>, app: Data<AppState>) -> HttpResponse {
+    let address = match Address::parse_with_prefix(&address, &app.spec.address_prefix) {
+        Ok(address) => address,
+        Err(err) => return HttpResponse::BadRequest().json(err.to_string()),
+    };
+
+    let chain = app.chain.lock().unwrap();
+    let balance = match chain.balance_of(address.as_str()) {
+        Ok(balance) => balance,
+        Err(err) => return error_response(err),
+    };
+
+    HttpResponse::Ok().json(BalanceResponse { address, balance })
+}
+
+/// Nonce to sign the next transfer from the address with.
+#[get("/{address}/nonce")]
+async fn get_nonce(address: Path<String>, app: Data<AppState>) -> HttpResponse {
+    let address = match Address::parse_with_prefix(&address, &app.spec.address_prefix) {
+        Ok(address) => address,
+        Err(err) => return HttpResponse::BadRequest().json(err.to_string()),
+    };
+
+    let chain = app.chain.lock().unwrap();
+    let nonce = chain.next_nonce(address.as_str());
+
+    HttpResponse::Ok().json(NonceResponse { address, nonce })
+}
+
+/// Confirmed transactions sending to or from the address, oldest first.
+#[get("/{address}/transactions")]
+async fn get_transactions(address: Path<String>, app: Data<AppState>) -> HttpResponse {
+    let address = match Address::parse_with_prefix(&address, &app.spec.address_prefix) {
+        Ok(address) => address,
+        Err(err) => return HttpResponse::BadRequest().json(err.to_string()),
+    };
+
+    let chain = app.chain.lock().unwrap();
+    let transactions: Vec<AccountTransaction> = chain
+        .transactions_of(address.as_str())
+        .into_iter()
+        .map(|(location, transaction)| AccountTransaction {
+            block_index: location.block_index,
+            transaction,
+        })
+        .collect();
+
+    HttpResponse::Ok().json(transactions)
+}
+
+pub fn register_account_service() -> Scope {
+    scope("/account")
+        .service(get_balance)
+        .service(get_nonce)
+        .service(get_transactions)
+}
@@ -1,25 +1,168 @@
 use actix_web::{
     get, post,
-    web::{scope, Data},
+    web::{self, scope, Data, Path, Query},
     HttpResponse, Scope,
 };
 
-use crate::app::AppState;
+use serde::{Deserialize, Serialize};
 
+use crate::{
+    app::AppState,
+    blockchain::{
+        block::{Block, BlockHeader},
+        query::{BlockQuery, Order, DEFAULT_LIMIT},
+    },
+    miner::mine_block,
+    services::error_response,
+};
+
+/// Block together with its header hash, which is derived rather than stored.
+#[derive(Serialize)]
+pub struct BlockResponse<'a> {
+    hash: String,
+    #[serde(flatten)]
+    block: &'a Block,
+}
+
+impl<'a> From<&'a Block> for BlockResponse<'a> {
+    fn from(block: &'a Block) -> Self {
+        BlockResponse {
+            hash: block.hash(),
+            block,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MinedBlockResponse<'a> {
+    #[serde(flatten)]
+    block: BlockResponse<'a>,
+    hashrate: u64,
+}
+
+/// Mine pending transactions on the miner threads without blocking other
+/// requests, returning the tip unchanged when there is nothing to mine.
 #[post("/mine-new-block")]
 async fn mine_new_block(app: Data<AppState>) -> HttpResponse {
-    let mut chain = app.chain.lock().unwrap();
-    let block = chain.mine_new_block();
-    HttpResponse::Ok().json(block)
+    let (chain, miner) = (app.chain.clone(), app.miner.clone());
+    let mined = web::block(move || mine_block(&chain, &miner, false, || true)).await;
+
+    match mined {
+        Ok(Ok(Some(mined))) => HttpResponse::Ok().json(MinedBlockResponse {
+            block: BlockResponse::from(&mined.block),
+            hashrate: mined.hashrate,
+        }),
+        Ok(Ok(None)) => {
+            let chain = app.chain.lock().unwrap();
+            HttpResponse::Ok().json(BlockResponse::from(chain.last_block()))
+        }
+        Ok(Err(err)) => error_response(err),
+        Err(err) => HttpResponse::InternalServerError().json(err.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListBlocksQuery {
+    from: Option<usize>,
+    limit: Option<usize>,
+    #[serde(default)]
+    order: Order,
+    since: Option<u64>,
+    until: Option<u64>,
+    miner: Option<String>,
+    /// Leave out the transactions of each block.
+    #[serde(default)]
+    headers_only: bool,
+}
+
+/// Header of a block with its hash, listed without the transactions.
+#[derive(Serialize)]
+pub struct HeaderResponse<'a> {
+    hash: String,
+    header: &'a BlockHeader,
+    tx_count: usize,
 }
 
+impl<'a> From<&'a Block> for HeaderResponse<'a> {
+    fn from(block: &'a Block) -> Self {
+        HeaderResponse {
+            hash: block.hash(),
+            header: &block.header,
+            tx_count: block.tx_count,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ListedBlock<'a> {
+    Block(BlockResponse<'a>),
+    Header(HeaderResponse<'a>),
+}
+
+#[derive(Serialize)]
+pub struct BlockListResponse<'a> {
+    /// Number of blocks in the chain.
+    height: usize,
+    /// Value of `from` that gets the next page, if there is one.
+    next: Option<usize>,
+    blocks: Vec<ListedBlock<'a>>,
+}
+
+/// Page of blocks, oldest first unless `order=desc`, filtered by time range
+/// and by the miner paid the reward.
 #[get("/list-blocks")]
-async fn list_blocks(app: Data<AppState>) -> HttpResponse {
+async fn list_blocks(query: Query<ListBlocksQuery>, app: Data<AppState>) -> HttpResponse {
+    let query = query.into_inner();
+    let block_query = BlockQuery {
+        from: query.from,
+        limit: query.limit.unwrap_or(DEFAULT_LIMIT),
+        order: query.order,
+        since: query.since,
+        until: query.until,
+        miner: query.miner,
+    };
+
     let chain = app.chain.lock().unwrap();
-    let blocks = chain.blocks();
-    HttpResponse::Ok().json(blocks)
+    let page = chain.list_blocks(&block_query);
+    let blocks = page
+        .blocks
+        .into_iter()
+        .map(|block| {
+            if query.headers_only {
+                ListedBlock::Header(HeaderResponse::from(block))
+            } else {
+                ListedBlock::Block(BlockResponse::from(block))
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(BlockListResponse {
+        height: chain.height(),
+        next: page.next,
+        blocks,
+    })
+}
+
+/// Block by its header hash or, for anything shorter than a hash, its index.
+#[get("/{hash_or_index}")]
+async fn get_block(hash_or_index: Path<String>, app: Data<AppState>) -> HttpResponse {
+    let chain = app.chain.lock().unwrap();
+
+    let block = match hash_or_index.parse::<usize>() {
+        Ok(index) if hash_or_index.len() < 64 => chain.get_block(index),
+        _ => chain.get_block_by_hash(&hash_or_index),
+    };
+
+    match block {
+        Some(block) => HttpResponse::Ok().json(BlockResponse::from(block)),
+        None => HttpResponse::NotFound().json("Not found"),
+    }
 }
 
 pub fn register_block_service() -> Scope {
-    scope("/block").service(mine_new_block).service(list_blocks)
+    scope("/block")
+        .service(mine_new_block)
+        .service(list_blocks)
+        .service(get_block)
 }
@@ -5,26 +5,41 @@ use actix_web::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::app::AppState;
+use crate::{
+    app::AppState,
+    blockchain::{amount::Amount, chain::Chain, target::Target},
+    services::error_response,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct SetRewardRequest {
+    new_value: Amount,
+}
 
 #[derive(Serialize, Deserialize)]
-pub struct SetRequest {
-    new_value: f64,
+pub struct SetDifficultyRequest {
+    new_value: usize,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct ChainRewardResponse {
-    value: f64,
+    value: Amount,
 }
 #[derive(Serialize, Deserialize)]
 pub struct ChainDifficultyResponse {
     value: usize,
+    target: Target,
+    dev_mode: bool,
 }
 
 #[post("/set-reward")]
-async fn set_chain_reward(app: Data<AppState>, body: Json<SetRequest>) -> HttpResponse {
+async fn set_chain_reward(app: Data<AppState>, body: Json<SetRewardRequest>) -> HttpResponse {
     let mut chain = app.chain.lock().unwrap();
-    chain.set_reward(body.new_value);
+    if let Err(err) = chain.set_reward(body.new_value) {
+        return error_response(err);
+    }
+    // Templates being mined carry the old reward
+    app.miner.cancel();
 
     HttpResponse::Ok().json(ChainRewardResponse {
         value: chain.reward(),
@@ -40,22 +55,45 @@ async fn get_chain_reward(app: Data<AppState>) -> HttpResponse {
 }
 
 #[post("/set-difficulty")]
-async fn set_chain_difficulty(app: Data<AppState>, body: Json<SetRequest>) -> HttpResponse {
+async fn set_chain_difficulty(
+    app: Data<AppState>,
+    body: Json<SetDifficultyRequest>,
+) -> HttpResponse {
     let mut chain = app.chain.lock().unwrap();
-    chain.set_difficulty(body.new_value as usize);
+    if let Err(err) = chain.set_difficulty(body.new_value) {
+        return error_response(err);
+    }
+    app.miner.cancel();
 
-    HttpResponse::Ok().json(ChainRewardResponse {
-        value: chain.reward(),
-    })
+    HttpResponse::Ok().json(difficulty_response(&chain))
+}
+
+#[get("/validate")]
+async fn validate_chain(app: Data<AppState>) -> HttpResponse {
+    let chain = app.chain.lock().unwrap();
+
+    HttpResponse::Ok().json(chain.validate())
+}
+
+/// Spec of the network, with the chain id transactions have to be signed for.
+#[get("/spec")]
+async fn get_chain_spec(app: Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(&app.spec)
 }
 
 #[get("/get-difficulty")]
 async fn get_chain_difficulty(app: Data<AppState>) -> HttpResponse {
     let chain = app.chain.lock().unwrap();
 
-    HttpResponse::Ok().json(ChainDifficultyResponse {
+    HttpResponse::Ok().json(difficulty_response(&chain))
+}
+
+fn difficulty_response(chain: &Chain) -> ChainDifficultyResponse {
+    ChainDifficultyResponse {
         value: chain.difficulty(),
-    })
+        target: chain.target(),
+        dev_mode: chain.dev_mode(),
+    }
 }
 
 pub fn register_chain_service() -> Scope {
@@ -64,4 +102,6 @@ pub fn register_chain_service() -> Scope {
         .service(set_chain_difficulty)
         .service(get_chain_reward)
         .service(get_chain_difficulty)
+        .service(validate_chain)
+        .service(get_chain_spec)
 }
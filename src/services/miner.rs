@@ -0,0 +1,37 @@
+use actix_web::{
+    get, post,
+    web::{self, scope, Data},
+    HttpResponse, Scope,
+};
+
+use crate::app::AppState;
+
+#[post("/start")]
+async fn start_miner(app: Data<AppState>) -> HttpResponse {
+    app.auto_miner.start();
+
+    HttpResponse::Ok().json(app.auto_miner.status())
+}
+
+/// Stopping waits for the loop to finish, so it runs off the async workers.
+#[post("/stop")]
+async fn stop_miner(app: Data<AppState>) -> HttpResponse {
+    let stop_app = app.clone();
+    if let Err(err) = web::block(move || stop_app.auto_miner.stop()).await {
+        return HttpResponse::InternalServerError().json(err.to_string());
+    }
+
+    HttpResponse::Ok().json(app.auto_miner.status())
+}
+
+#[get("/status")]
+async fn miner_status(app: Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(app.auto_miner.status())
+}
+
+pub fn register_miner_service() -> Scope {
+    scope("/miner")
+        .service(start_miner)
+        .service(stop_miner)
+        .service(miner_status)
+}
@@ -0,0 +1,91 @@
+use actix_web::{
+    get, post,
+    web::{scope, Data, Json, Query},
+    HttpResponse, Scope,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppState,
+    blockchain::{block::BlockHeader, transaction::Transaction},
+    services::{block::BlockResponse, error_response},
+    wallet::Address,
+};
+
+#[derive(Deserialize)]
+pub struct TemplateQuery {
+    /// Address paid the block reward, the node's miner address if not given.
+    address: Option<String>,
+}
+
+/// Unsolved block for an external miner. The miner searches for a nonce that
+/// makes the sha256 of the bincode encoded `header` meet `header.target`.
+#[derive(Serialize)]
+pub struct TemplateResponse {
+    header: BlockHeader,
+    coinbase_address: String,
+    transactions: Vec<Transaction>,
+}
+
+#[derive(Deserialize)]
+pub struct SubmitRequest {
+    header: BlockHeader,
+}
+
+#[get("/get-template")]
+async fn get_template(query: Query<TemplateQuery>, app: Data<AppState>) -> HttpResponse {
+    let address = query
+        .address
+        .as_deref()
+        .map(|address| Address::parse_with_prefix(address, &app.spec.address_prefix))
+        .transpose();
+    let address = match address {
+        Ok(address) => address,
+        Err(err) => return HttpResponse::BadRequest().json(err.to_string()),
+    };
+    let template = {
+        let chain = app.chain.lock().unwrap();
+        match &address {
+            Some(address) => chain.block_template_for(address),
+            None => chain.block_template(),
+        }
+    };
+
+    let response = TemplateResponse {
+        header: template.header.clone(),
+        coinbase_address: template
+            .txs
+            .last()
+            .and_then(|reward| reward.tx_data.receiver())
+            .unwrap_or_default()
+            .to_string(),
+        transactions: template.txs.clone(),
+    };
+    app.templates.lock().unwrap().insert(template);
+
+    HttpResponse::Ok().json(response)
+}
+
+/// Append the block a solved template header belongs to.
+#[post("/submit")]
+async fn submit_block(body: Json<SubmitRequest>, app: Data<AppState>) -> HttpResponse {
+    let SubmitRequest { header } = body.into_inner();
+    let Some(block) = app.templates.lock().unwrap().complete(header) else {
+        return HttpResponse::NotFound().json("Unknown template");
+    };
+
+    let mut chain = app.chain.lock().unwrap();
+    match chain.submit_block(block) {
+        Ok(block) => {
+            // Work on the old tip, local or external, is now stale
+            app.miner.cancel();
+            app.templates.lock().unwrap().clear();
+            HttpResponse::Ok().json(BlockResponse::from(block))
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+pub fn register_mining_service() -> Scope {
+    scope("/mining").service(get_template).service(submit_block)
+}
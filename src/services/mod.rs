@@ -1,3 +1,36 @@
+use actix_web::HttpResponse;
+
+use crate::blockchain::{error::ChainError, mempool::MempoolError};
+
+pub mod account;
 pub mod block;
 pub mod chain;
+pub mod miner;
+pub mod mining;
+pub mod node;
 pub mod transactions;
+
+/// Map a chain error onto the HTTP response returned to the client.
+pub fn error_response(err: ChainError) -> HttpResponse {
+    match err {
+        ChainError::TransactionNotVerified(_) => HttpResponse::Forbidden().json(err.to_string()),
+        ChainError::TransactionHashMismatch
+        | ChainError::InvalidTransactionType
+        | ChainError::InvalidAmount
+        | ChainError::InsufficientFunds { .. }
+        | ChainError::FeeTooLow { .. }
+        | ChainError::InvalidNonce { .. }
+        | ChainError::Amount(_)
+        | ChainError::InvalidBlock(_) => HttpResponse::BadRequest().json(err.to_string()),
+        ChainError::Mempool(MempoolError::TooLarge { .. }) => {
+            HttpResponse::PayloadTooLarge().json(err.to_string())
+        }
+        ChainError::Mempool(_) => HttpResponse::ServiceUnavailable().json(err.to_string()),
+        ChainError::DuplicateTransaction
+        | ChainError::StaleBlock
+        | ChainError::KnownBlock
+        | ChainError::UnknownParent => HttpResponse::Conflict().json(err.to_string()),
+        ChainError::DevModeOnly => HttpResponse::Forbidden().json(err.to_string()),
+        ChainError::Storage(_) => HttpResponse::InternalServerError().json(err.to_string()),
+    }
+}
@@ -0,0 +1,80 @@
+use std::net::SocketAddr;
+
+use actix_web::{
+    get, post,
+    web::{self, scope, Data, Json},
+    HttpResponse, Scope,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppState,
+    p2p::{BlockSync, PeerInfo},
+};
+
+#[derive(Serialize)]
+pub struct PeersResponse {
+    /// Address the node accepts peers on, `None` when networking is off.
+    listen_addr: Option<SocketAddr>,
+    peers: Vec<PeerInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct ConnectRequest {
+    addr: SocketAddr,
+}
+
+#[get("/peers")]
+async fn list_peers(app: Data<AppState>) -> HttpResponse {
+    let response = match &app.node {
+        Some(node) => PeersResponse {
+            listen_addr: Some(node.local_addr()),
+            peers: node.peers(),
+        },
+        None => PeersResponse {
+            listen_addr: None,
+            peers: Vec::new(),
+        },
+    };
+
+    HttpResponse::Ok().json(response)
+}
+
+/// Progress catching up with the chains of our peers.
+#[get("/sync-status")]
+async fn sync_status(app: Data<AppState>) -> HttpResponse {
+    let status = match &app.node {
+        Some(node) => node.sync_status(),
+        None => {
+            let height = app.chain.lock().unwrap().height();
+            BlockSync::new().status(height, height)
+        }
+    };
+
+    HttpResponse::Ok().json(status)
+}
+
+/// Connect to another node, waiting for the handshake off the async workers.
+#[post("/connect")]
+async fn connect_peer(body: Json<ConnectRequest>, app: Data<AppState>) -> HttpResponse {
+    if app.node.is_none() {
+        return HttpResponse::Conflict().json("P2P networking is disabled");
+    }
+
+    let addr = body.addr;
+    let connecting = app.clone();
+    let connected = web::block(move || connecting.node.as_ref().unwrap().connect(addr)).await;
+
+    match connected {
+        Ok(Ok(peer)) => HttpResponse::Ok().json(peer),
+        Ok(Err(err)) => HttpResponse::BadGateway().json(err.to_string()),
+        Err(err) => HttpResponse::InternalServerError().json(err.to_string()),
+    }
+}
+
+pub fn register_node_service() -> Scope {
+    scope("/node")
+        .service(list_peers)
+        .service(sync_status)
+        .service(connect_peer)
+}
@@ -8,17 +8,26 @@ use serde::{Deserialize, Serialize};
 use crate::{
     app::AppState,
     blockchain::{
+        amount::Amount,
         chain::Chain,
+        merkle::MerkleProof,
         models::TransactionData,
         transaction::{Transaction, TransactionType},
     },
+    services::error_response,
+    wallet::Address,
 };
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateTransactionRequest {
     sender: String,
     receiver: String,
-    amount: f64,
+    amount: Amount,
+    #[serde(default)]
+    fee: Amount,
+    nonce: u64,
+    timestamp: u64,
+    public_key: String,
     signature: String,
     tx_type: TransactionType,
 }
@@ -34,25 +43,34 @@ async fn create_transaction(
     app: Data<AppState>,
     new_tx: Json<CreateTransactionRequest>,
 ) -> HttpResponse {
+    // Only addresses of the network the node runs on
+    let parse = |address: &str| Address::parse_with_prefix(address, &app.spec.address_prefix);
+    let (sender, receiver) = match (parse(&new_tx.sender), parse(&new_tx.receiver)) {
+        (Ok(sender), Ok(receiver)) => (sender, receiver),
+        (Err(err), _) | (_, Err(err)) => return HttpResponse::BadRequest().json(err.to_string()),
+    };
     let tx_data = TransactionData::TransferData {
-        sender: new_tx.sender.clone(),
-        receiver: new_tx.receiver.clone(),
+        sender: sender.to_string(),
+        receiver: receiver.to_string(),
         amount: new_tx.amount,
+        fee: new_tx.fee,
+        nonce: new_tx.nonce,
     };
-    let mut transaction = Chain::new_transaction(tx_data, new_tx.tx_type);
+    let mut transaction = Chain::new_transaction_at(tx_data, new_tx.tx_type, new_tx.timestamp);
 
     let mut chain = app.chain.lock().unwrap();
 
-    // Return http error if transaction not verifiable
-    match chain.add_transaction(&mut transaction, &new_tx.sender, &new_tx.signature) {
+    // Return http error if transaction not verifiable or not spendable
+    match chain.add_transaction(&mut transaction, &new_tx.public_key, &new_tx.signature) {
         Ok(tx) => HttpResponse::Ok().json(CreateTransactionResponse {
-            next_index: chain.current_tx().len(),
+            next_index: chain.mempool().len(),
             transaction: tx.clone(),
         }),
-        Err(_) => HttpResponse::Forbidden().json("Transaction not verified"),
+        Err(err) => error_response(err),
     }
 }
 
+/// Pending transactions in the order they would be mined.
 #[get("/list-current-transactions")]
 async fn list_current_transactions(app: Data<AppState>) -> HttpResponse {
     let chain = app.chain.lock().unwrap();
@@ -61,6 +79,27 @@ async fn list_current_transactions(app: Data<AppState>) -> HttpResponse {
     HttpResponse::Ok().json(transactions)
 }
 
+#[derive(Serialize)]
+pub struct TransactionProofResponse {
+    block_index: usize,
+    merkle_root: String,
+    proof: MerkleProof,
+}
+
+#[get("/{tx_hash}/proof")]
+async fn get_transaction_proof(tx_hash: Path<String>, app: Data<AppState>) -> HttpResponse {
+    let chain = app.chain.lock().unwrap();
+
+    match chain.merkle_proof(&tx_hash) {
+        Some((block, proof)) => HttpResponse::Ok().json(TransactionProofResponse {
+            block_index: block.header.index,
+            merkle_root: block.header.merkle_root.clone(),
+            proof,
+        }),
+        None => HttpResponse::NotFound().json("Not found"),
+    }
+}
+
 #[get("/{tx_hash}")]
 async fn get_transaction(tx_hash: Path<String>, app: Data<AppState>) -> HttpResponse {
     let chain = app.chain.lock().unwrap();
@@ -77,5 +116,6 @@ pub fn register_transaction_service() -> Scope {
     scope("transaction")
         .service(create_transaction)
         .service(list_current_transactions)
+        .service(get_transaction_proof)
         .service(get_transaction)
 }
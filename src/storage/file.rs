@@ -0,0 +1,426 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use super::{BlockStore, MemoryStore};
+use crate::blockchain::{block::Block, transaction::Transaction};
+
+const BLOCKS_FILE: &str = "blocks.dat";
+const PENDING_FILE: &str = "pending.dat";
+const PENDING_TMP_FILE: &str = "pending.dat.tmp";
+
+// Each record is `[payload length: u32 LE][sha256(payload)][payload]`
+const LEN_SIZE: usize = 4;
+const CHECKSUM_SIZE: usize = 32;
+const RECORD_HEADER_SIZE: usize = LEN_SIZE + CHECKSUM_SIZE;
+// Far beyond any block a chain accepts, so a longer length can only be damage
+const MAX_RECORD_LEN: usize = 64 * 1024 * 1024;
+
+/// Append-only on-disk block store.
+///
+/// Blocks are appended to `blocks.dat` as length-prefixed, checksummed bincode
+/// records and fsynced before `append_block` returns. A last record left torn by
+/// a crash is detected when the store is opened and truncated away, so the file
+/// always ends on a complete block. A damaged record followed by others, or with
+/// a length no block could have, is not a crash and fails the open instead,
+/// leaving the file untouched. The pending pool is small and rewritten as a
+/// whole via a temporary file and rename.
+///
+/// All blocks are loaded into an in-memory cache on open and reads are served
+/// from it.
+pub struct FileStore {
+    dir: PathBuf,
+    blocks_file: File,
+    // Offset in the blocks file at which the record of each block ends
+    record_ends: Vec<u64>,
+    cache: MemoryStore,
+}
+
+impl FileStore {
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let blocks_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(BLOCKS_FILE))?;
+
+        let mut store = FileStore {
+            dir,
+            blocks_file,
+            record_ends: Vec::new(),
+            cache: MemoryStore::new(),
+        };
+        store.load()?;
+        Ok(store)
+    }
+
+    // ---
+    // Private methods
+    // ---
+
+    /// Load all blocks into the cache, truncating a torn or corrupt last record
+    /// of the blocks file.
+    fn load(&mut self) -> io::Result<()> {
+        let bytes = self.read_blocks_file()?;
+        let (payloads, valid_len) = FileStore::records(&bytes)?;
+
+        let mut end = 0;
+        for payload in payloads {
+            let block = bincode::deserialize(payload)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            self.cache.append_block(block)?;
+            end += (RECORD_HEADER_SIZE + payload.len()) as u64;
+            self.record_ends.push(end);
+        }
+
+        if valid_len < bytes.len() {
+            self.blocks_file.set_len(valid_len as u64)?;
+            self.blocks_file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self, block: &Block) -> io::Result<()> {
+        let payload = bincode::serialize(block).map_err(io::Error::other)?;
+        if payload.len() > MAX_RECORD_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "block too large",
+            ));
+        }
+        let len = payload.len() as u32;
+
+        // Build the whole record up front so it goes out in a single write
+        let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + payload.len());
+        record.extend_from_slice(&len.to_le_bytes());
+        record.extend_from_slice(&Sha256::digest(&payload));
+        record.extend_from_slice(&payload);
+
+        let start = self.blocks_file.seek(SeekFrom::End(0))?;
+        let written = self
+            .blocks_file
+            .write_all(&record)
+            .and_then(|()| self.blocks_file.sync_data());
+        if let Err(err) = written {
+            // Drop whatever part of the record made it out, so the next block
+            // is not appended after a torn one
+            self.blocks_file.set_len(start)?;
+            return Err(err);
+        }
+        self.record_ends.push(start + record.len() as u64);
+        Ok(())
+    }
+
+    fn read_blocks_file(&mut self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.blocks_file.seek(SeekFrom::Start(0))?;
+        self.blocks_file.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Split `bytes` into record payloads, stopping at an incomplete or corrupt
+    /// last record. Also returns the length of the valid prefix.
+    ///
+    /// Fails on a corrupt record followed by more data, or on a length longer
+    /// than any record, which a torn write cannot leave behind. The length is
+    /// not covered by the checksum, so only a plausible one is taken to run
+    /// past the end of a torn last record.
+    fn records(bytes: &[u8]) -> io::Result<(Vec<&[u8]>, usize)> {
+        let mut payloads = Vec::new();
+        let mut offset = 0;
+
+        while bytes.len() - offset >= RECORD_HEADER_SIZE {
+            let len_bytes: [u8; LEN_SIZE] = bytes[offset..offset + LEN_SIZE].try_into().unwrap();
+            let len = u32::from_le_bytes(len_bytes) as usize;
+            let checksum = &bytes[offset + LEN_SIZE..offset + RECORD_HEADER_SIZE];
+
+            if len > MAX_RECORD_LEN {
+                return Err(FileStore::corrupt_record(payloads.len()));
+            }
+
+            let start = offset + RECORD_HEADER_SIZE;
+            if bytes.len() - start < len {
+                break;
+            }
+
+            let payload = &bytes[start..start + len];
+            if Sha256::digest(payload).as_slice() != checksum {
+                if start + len < bytes.len() {
+                    return Err(FileStore::corrupt_record(payloads.len()));
+                }
+                break;
+            }
+
+            payloads.push(payload);
+            offset = start + len;
+        }
+
+        Ok((payloads, offset))
+    }
+
+    fn corrupt_record(index: usize) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("corrupt block record {index} in blocks file"),
+        )
+    }
+}
+
+impl BlockStore for FileStore {
+    fn append_block(&mut self, block: Block) -> io::Result<()> {
+        // Only cache the block once it is durable on disk
+        self.write_block(&block)?;
+        self.cache.append_block(block)
+    }
+
+    fn truncate(&mut self, height: usize) -> io::Result<()> {
+        if height >= self.record_ends.len() {
+            return Ok(());
+        }
+
+        let len = height
+            .checked_sub(1)
+            .map_or(0, |last| self.record_ends[last]);
+        self.blocks_file.set_len(len)?;
+        self.blocks_file.sync_data()?;
+        self.record_ends.truncate(height);
+        self.cache.truncate(height)
+    }
+
+    fn get_block(&self, index: usize) -> Option<&Block> {
+        self.cache.get_block(index)
+    }
+
+    fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+        self.cache.get_block_by_hash(hash)
+    }
+
+    fn iter_range(&self, range: Range<usize>) -> Box<dyn Iterator<Item = &Block> + '_> {
+        self.cache.iter_range(range)
+    }
+
+    fn height(&self) -> usize {
+        self.cache.height()
+    }
+
+    fn load_pending(&self) -> io::Result<Vec<Transaction>> {
+        match fs::read(self.dir.join(PENDING_FILE)) {
+            Ok(bytes) => bincode::deserialize(&bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn save_pending(&mut self, txs: &[Transaction]) -> io::Result<()> {
+        let bytes = bincode::serialize(txs).map_err(io::Error::other)?;
+
+        // Write to a temporary file first so a crash never leaves a half written pool
+        let tmp_path = self.dir.join(PENDING_TMP_FILE);
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(&bytes)?;
+        tmp.sync_data()?;
+
+        fs::rename(tmp_path, self.dir.join(PENDING_FILE))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::{new_block, new_tx};
+
+    #[test]
+    fn append_and_load_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = FileStore::open(dir.path()).unwrap();
+        assert_eq!(store.height(), 0);
+
+        store.append_block(new_block(0)).unwrap();
+        store.append_block(new_block(1)).unwrap();
+
+        let store = FileStore::open(dir.path()).unwrap();
+
+        assert_eq!(store.height(), 2);
+        assert_eq!(store.get_block(0).unwrap().header.index, 0);
+        assert_eq!(store.tip().unwrap().header.index, 1);
+        assert_eq!(
+            store.tip().unwrap().txs[0].hash.to_string(),
+            new_tx().hash.to_string()
+        );
+    }
+
+    #[test]
+    fn truncate_torn_tail() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = FileStore::open(dir.path()).unwrap();
+        store.append_block(new_block(0)).unwrap();
+        store.append_block(new_block(1)).unwrap();
+        drop(store);
+
+        // Simulate a crash half way through writing the last record
+        let path = dir.path().join(BLOCKS_FILE);
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 10)
+            .unwrap();
+
+        let mut store = FileStore::open(dir.path()).unwrap();
+        assert_eq!(store.height(), 1);
+
+        // Chain continues cleanly after the truncated record
+        store.append_block(new_block(1)).unwrap();
+        let store = FileStore::open(dir.path()).unwrap();
+        assert_eq!(store.height(), 2);
+    }
+
+    #[test]
+    fn truncate_corrupt_tail() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = FileStore::open(dir.path()).unwrap();
+        store.append_block(new_block(0)).unwrap();
+        store.append_block(new_block(1)).unwrap();
+        drop(store);
+
+        // Flip the last byte of the last record
+        let path = dir.path().join(BLOCKS_FILE);
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        let store = FileStore::open(dir.path()).unwrap();
+        assert_eq!(store.height(), 1);
+    }
+
+    #[test]
+    fn refuse_corrupt_middle() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = FileStore::open(dir.path()).unwrap();
+        for index in 0..3 {
+            store.append_block(new_block(index)).unwrap();
+        }
+        let second_end = store.record_ends[1] as usize;
+        drop(store);
+
+        // Flip the last byte of the second record
+        let path = dir.path().join(BLOCKS_FILE);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[second_end - 1] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+
+        let err = FileStore::open(dir.path()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // The blocks after it are still on disk
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn refuse_corrupt_length() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = FileStore::open(dir.path()).unwrap();
+        for index in 0..3 {
+            store.append_block(new_block(index)).unwrap();
+        }
+        let first_end = store.record_ends[0] as usize;
+        drop(store);
+
+        // Flip the top byte of the length of the second record, which then
+        // seems to run past the end of the file
+        let path = dir.path().join(BLOCKS_FILE);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[first_end + LEN_SIZE - 1] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+
+        let err = FileStore::open(dir.path()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn truncate_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut store = FileStore::open(dir.path()).unwrap();
+        for index in 0..3 {
+            store.append_block(new_block(index)).unwrap();
+        }
+        store.truncate(1).unwrap();
+        assert_eq!(store.height(), 1);
+
+        // Blocks appended after truncating replace the removed ones on disk
+        store.append_block(new_block(1)).unwrap();
+        let mut store = FileStore::open(dir.path()).unwrap();
+        assert_eq!(store.height(), 2);
+
+        store.truncate(0).unwrap();
+        let store = FileStore::open(dir.path()).unwrap();
+        assert_eq!(store.height(), 0);
+    }
+
+    #[test]
+    fn save_and_load_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = FileStore::open(dir.path()).unwrap();
+
+        assert!(store.load_pending().unwrap().is_empty());
+
+        store.save_pending(&[new_tx(), new_tx()]).unwrap();
+        let pending = FileStore::open(dir.path()).unwrap().load_pending().unwrap();
+        assert_eq!(pending.len(), 2);
+
+        store.save_pending(&[]).unwrap();
+        assert!(store.load_pending().unwrap().is_empty());
+    }
+
+    mod test_utils {
+        use crate::blockchain::{
+            amount::Amount,
+            block::Block,
+            hasher::{Hash, Hasher},
+            models::TransactionData,
+            target::Target,
+            transaction::{Transaction, TransactionType},
+        };
+
+        pub fn new_tx() -> Transaction {
+            let tx_data = TransactionData::TransferData {
+                sender: "me".to_string(),
+                receiver: "you".to_string(),
+                amount: Amount::from_coins(10).unwrap(),
+                fee: Amount::ZERO,
+                nonce: 0,
+            };
+            let mut hash_buf = Hash::new();
+            let hash = Hasher::hash_tx_data(&tx_data, 1, &mut hash_buf);
+            Transaction::new(tx_data, TransactionType::Transfer, 1, hash.to_owned())
+        }
+
+        pub fn new_block(index: usize) -> Block {
+            Block::new(
+                index,
+                1,
+                vec![new_tx()],
+                "merkle",
+                "prev_hash",
+                Target::MAX,
+                Amount::ZERO,
+            )
+        }
+    }
+}
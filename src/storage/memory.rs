@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::io;
+use std::ops::Range;
+
+use super::BlockStore;
+use crate::blockchain::{block::Block, transaction::Transaction};
+
+/// Block store that lives entirely in memory, used for tests and as the block
+/// cache of `FileStore`.
+#[derive(Default)]
+pub struct MemoryStore {
+    blocks: Vec<Block>,
+    hash_index: HashMap<String, usize>,
+    pending: Vec<Transaction>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlockStore for MemoryStore {
+    fn append_block(&mut self, block: Block) -> io::Result<()> {
+        self.hash_index.insert(block.hash(), self.blocks.len());
+        self.blocks.push(block);
+        Ok(())
+    }
+
+    fn truncate(&mut self, height: usize) -> io::Result<()> {
+        for block in self.blocks.drain(height.min(self.blocks.len())..) {
+            self.hash_index.remove(&block.hash());
+        }
+        Ok(())
+    }
+
+    fn get_block(&self, index: usize) -> Option<&Block> {
+        self.blocks.get(index)
+    }
+
+    fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+        self.hash_index
+            .get(hash)
+            .and_then(|index| self.blocks.get(*index))
+    }
+
+    fn iter_range(&self, range: Range<usize>) -> Box<dyn Iterator<Item = &Block> + '_> {
+        let end = range.end.min(self.blocks.len());
+        let start = range.start.min(end);
+        Box::new(self.blocks[start..end].iter())
+    }
+
+    fn height(&self) -> usize {
+        self.blocks.len()
+    }
+
+    fn load_pending(&self) -> io::Result<Vec<Transaction>> {
+        Ok(self.pending.clone())
+    }
+
+    fn save_pending(&mut self, txs: &[Transaction]) -> io::Result<()> {
+        self.pending = txs.to_vec();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{amount::Amount, target::Target};
+
+    fn new_block(index: usize, merkle_root: &str) -> Block {
+        Block::new(
+            index,
+            1,
+            Vec::new(),
+            merkle_root,
+            "prev_hash",
+            Target::MAX,
+            Amount::ZERO,
+        )
+    }
+
+    #[test]
+    fn append_and_get_blocks() {
+        let mut store = MemoryStore::new();
+        assert_eq!(store.height(), 0);
+        assert!(store.tip().is_none());
+
+        store.append_block(new_block(0, "root_0")).unwrap();
+        store.append_block(new_block(1, "root_1")).unwrap();
+        store.append_block(new_block(2, "root_2")).unwrap();
+
+        assert_eq!(store.height(), 3);
+        assert_eq!(store.tip().unwrap().header.index, 2);
+        assert_eq!(store.get_block(1).unwrap().header.merkle_root, "root_1");
+        assert!(store.get_block(3).is_none());
+        let hash = store.get_block(2).unwrap().hash();
+        assert_eq!(store.get_block_by_hash(&hash).unwrap().header.index, 2);
+        assert!(store.get_block_by_hash("root_2").is_none());
+        assert!(store.get_block_by_hash("missing").is_none());
+    }
+
+    #[test]
+    fn truncate_blocks() {
+        let mut store = MemoryStore::new();
+        for index in 0..3 {
+            store
+                .append_block(new_block(index, &format!("root_{index}")))
+                .unwrap();
+        }
+        let hash = store.get_block(2).unwrap().hash();
+
+        store.truncate(2).unwrap();
+        assert_eq!(store.height(), 2);
+        assert!(store.get_block_by_hash(&hash).is_none());
+
+        store.truncate(5).unwrap();
+        assert_eq!(store.height(), 2);
+    }
+
+    #[test]
+    fn iter_range() {
+        let mut store = MemoryStore::new();
+        for index in 0..5 {
+            store
+                .append_block(new_block(index, &format!("root_{index}")))
+                .unwrap();
+        }
+
+        let indexes: Vec<usize> = store.iter_range(1..3).map(|b| b.header.index).collect();
+        assert_eq!(indexes, vec![1, 2]);
+
+        // Out of bounds ranges are clamped
+        assert_eq!(store.iter_range(3..10).count(), 2);
+        assert_eq!(store.iter_range(7..10).count(), 0);
+    }
+}
@@ -1 +1,42 @@
-pub struct Storage;
+use std::io;
+use std::ops::Range;
+
+use crate::blockchain::{block::Block, transaction::Transaction};
+
+mod file;
+mod memory;
+
+pub use file::FileStore;
+pub use memory::MemoryStore;
+
+/// Storage backend for the blocks of a chain and its pending transaction pool.
+///
+/// Blocks are appended to the tip and only removed from it again when the chain
+/// switches over to another branch, so the block at `index` is always the
+/// `index`th block of the chain.
+pub trait BlockStore: Send {
+    fn append_block(&mut self, block: Block) -> io::Result<()>;
+
+    /// Remove every block from `height` on.
+    fn truncate(&mut self, height: usize) -> io::Result<()>;
+
+    fn get_block(&self, index: usize) -> Option<&Block>;
+
+    /// Find a block by the hash of its header.
+    fn get_block_by_hash(&self, hash: &str) -> Option<&Block>;
+
+    fn iter_range(&self, range: Range<usize>) -> Box<dyn Iterator<Item = &Block> + '_>;
+
+    /// Number of blocks in the store.
+    fn height(&self) -> usize;
+
+    fn tip(&self) -> Option<&Block> {
+        self.height()
+            .checked_sub(1)
+            .and_then(|index| self.get_block(index))
+    }
+
+    fn load_pending(&self) -> io::Result<Vec<Transaction>>;
+
+    fn save_pending(&mut self, txs: &[Transaction]) -> io::Result<()>;
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Prefix of the addresses of the default network.
+pub const ADDRESS_PREFIX: &str = "rc";
+pub const ADDRESS_VERSION: u8 = 0;
+
+const PAYLOAD_LEN: usize = 20;
+const CHECKSUM_LEN: usize = 4;
+
+/// Checksummed account address derived from an Ed25519 public key.
+///
+/// Encoded as a human-readable prefix followed by the base58 encoding of
+/// `version || sha256(public key)[..20] || checksum`, where the checksum is the
+/// first four bytes of a double sha256 over the prefix, version and payload.
+///
+/// Parsing with `FromStr` or serde takes an address of any network, as the
+/// checksum pins its prefix. Input meant for one network is parsed with
+/// `parse_with_prefix` instead.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Address(String);
+
+impl Address {
+    /// Address of `key` on the default network.
+    pub fn from_public_key(key: &VerifyingKey) -> Self {
+        Address::with_prefix(key, ADDRESS_PREFIX)
+    }
+
+    /// Address of `key` on the network whose addresses start with `prefix`.
+    pub fn with_prefix(key: &VerifyingKey, prefix: &str) -> Self {
+        let digest = Sha256::digest(key.as_bytes());
+        let payload = &digest[..PAYLOAD_LEN];
+
+        let mut bytes = vec![ADDRESS_VERSION];
+        bytes.extend_from_slice(payload);
+        bytes.extend_from_slice(&Address::checksum(prefix, ADDRESS_VERSION, payload));
+
+        Address(format!("{prefix}{}", bs58::encode(bytes).into_string()))
+    }
+
+    /// Parse an address of the network whose addresses start with `prefix`.
+    pub fn parse_with_prefix(s: &str, prefix: &str) -> Result<Self, AddressError> {
+        let encoded = s.strip_prefix(prefix).ok_or(AddressError::InvalidPrefix)?;
+        let bytes = bs58::decode(encoded)
+            .into_vec()
+            .map_err(|_| AddressError::InvalidEncoding)?;
+
+        if bytes.len() != 1 + PAYLOAD_LEN + CHECKSUM_LEN {
+            return Err(AddressError::InvalidLength);
+        }
+
+        let version = bytes[0];
+        if version != ADDRESS_VERSION {
+            return Err(AddressError::UnsupportedVersion(version));
+        }
+
+        let (payload, checksum) = bytes[1..].split_at(PAYLOAD_LEN);
+        if checksum != Address::checksum(prefix, version, payload) {
+            return Err(AddressError::InvalidChecksum);
+        }
+
+        Ok(Address(s.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Start of the address, telling the network apart.
+    pub fn prefix(&self) -> &str {
+        &self.0[..Address::prefix_len(&self.0)]
+    }
+
+    // ---
+    // Private methods
+    // ---
+
+    fn checksum(prefix: &str, version: u8, payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+        let mut hasher = Sha256::new();
+        hasher.update(prefix.as_bytes());
+        hasher.update([version]);
+        hasher.update(payload);
+        let digest = Sha256::digest(hasher.finalize());
+
+        let mut checksum = [0u8; CHECKSUM_LEN];
+        checksum.copy_from_slice(&digest[..CHECKSUM_LEN]);
+        checksum
+    }
+
+    // Prefixes are lowercase letters, and the base58 encoding starts with the
+    // `1` of the zero version byte
+    fn prefix_len(s: &str) -> usize {
+        s.find(|c: char| !c.is_ascii_lowercase()).unwrap_or(s.len())
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Address::prefix_len(s) {
+            0 => Err(AddressError::InvalidPrefix),
+            len => Address::parse_with_prefix(s, &s[..len]),
+        }
+    }
+}
+
+impl TryFrom<String> for Address {
+    type Error = AddressError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Address> for String {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AddressError {
+    InvalidPrefix,
+    InvalidEncoding,
+    InvalidLength,
+    UnsupportedVersion(u8),
+    InvalidChecksum,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPrefix => write!(f, "address is not for this network"),
+            Self::InvalidEncoding => write!(f, "address is not valid base58"),
+            Self::InvalidLength => write!(f, "address has the wrong length"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported address version {version}")
+            }
+            Self::InvalidChecksum => write!(f, "address checksum does not match"),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    fn address() -> Address {
+        let key = SigningKey::from_bytes(&[1u8; 32]);
+        Address::from_public_key(&key.verifying_key())
+    }
+
+    #[test]
+    fn derive_and_parse_address() {
+        let address = address();
+
+        assert!(address.as_str().starts_with(ADDRESS_PREFIX));
+        assert_eq!(address.as_str().parse::<Address>(), Ok(address.clone()));
+
+        // Derivation is deterministic and unique per key
+        assert_eq!(address, self::address());
+        let other_key = SigningKey::from_bytes(&[2u8; 32]);
+        assert_ne!(
+            address,
+            Address::from_public_key(&other_key.verifying_key())
+        );
+    }
+
+    #[test]
+    fn parse_address_errors() {
+        let address = address().to_string();
+
+        assert_eq!(
+            "Nebula Miner".parse::<Address>(),
+            Err(AddressError::InvalidPrefix)
+        );
+        assert_eq!(
+            "rc0OIl".parse::<Address>(),
+            Err(AddressError::InvalidEncoding)
+        );
+        assert_eq!(
+            address[..address.len() - 2].parse::<Address>(),
+            Err(AddressError::InvalidLength)
+        );
+
+        // Changing a single character breaks the checksum
+        let mut chars: Vec<char> = address.chars().collect();
+        let last = chars.len() - 1;
+        chars[last] = if chars[last] == '2' { '3' } else { '2' };
+        let tampered: String = chars.into_iter().collect();
+        assert_eq!(
+            tampered.parse::<Address>(),
+            Err(AddressError::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn serde_address() {
+        let address = address();
+        let json = serde_json::to_string(&address).unwrap();
+
+        assert_eq!(json, format!("\"{address}\""));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+        assert!(serde_json::from_str::<Address>("\"Nebula Miner\"").is_err());
+    }
+
+    #[test]
+    fn network_prefix() {
+        let key = SigningKey::from_bytes(&[1u8; 32]).verifying_key();
+        let address = Address::with_prefix(&key, "rt");
+
+        assert!(address.as_str().starts_with("rt"));
+        assert_eq!(
+            Address::parse_with_prefix(address.as_str(), "rt"),
+            Ok(address.clone())
+        );
+        assert_eq!(
+            Address::parse_with_prefix(address.as_str(), ADDRESS_PREFIX),
+            Err(AddressError::InvalidPrefix)
+        );
+
+        // Without a network to parse for, the prefix is taken from the address
+        let parsed = address.as_str().parse::<Address>().unwrap();
+        assert_eq!(parsed, address);
+        assert_eq!(parsed.prefix(), "rt");
+
+        // The checksum covers the prefix, so swapping it is caught
+        let swapped = address.as_str().replacen("rt", ADDRESS_PREFIX, 1);
+        assert_eq!(
+            swapped.parse::<Address>(),
+            Err(AddressError::InvalidChecksum)
+        );
+    }
+}
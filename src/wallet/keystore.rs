@@ -0,0 +1,127 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::address::Address;
+
+const KEYSTORE_VERSION: u8 = 1;
+const KDF_ROUNDS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Secret key encrypted with a passphrase, as stored on disk.
+///
+/// The encryption key is derived from the passphrase with PBKDF2-HMAC-SHA256
+/// and the secret key sealed with ChaCha20-Poly1305, so a wrong passphrase or a
+/// modified file fails to decrypt rather than yielding a different key.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u8,
+    pub address: Address,
+    pub kdf_rounds: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    pub fn encrypt(secret: &[u8; 32], address: Address, passphrase: &str) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = Keystore::cipher(passphrase, &salt, KDF_ROUNDS);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), secret.as_slice())
+            .expect("Encrypting a 32 byte secret cannot fail");
+
+        Keystore {
+            version: KEYSTORE_VERSION,
+            address,
+            kdf_rounds: KDF_ROUNDS,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        }
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<[u8; 32], KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(self.version));
+        }
+
+        let salt = hex::decode(&self.salt).map_err(|_| KeystoreError::Malformed)?;
+        let nonce: [u8; NONCE_LEN] = hex::decode(&self.nonce)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(KeystoreError::Malformed)?;
+        let ciphertext = hex::decode(&self.ciphertext).map_err(|_| KeystoreError::Malformed)?;
+
+        let cipher = Keystore::cipher(passphrase, &salt, self.kdf_rounds);
+        let secret = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| KeystoreError::WrongPassphrase)?;
+
+        secret.try_into().map_err(|_| KeystoreError::Malformed)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), KeystoreError> {
+        let json = serde_json::to_vec_pretty(self).map_err(|_| KeystoreError::Malformed)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KeystoreError> {
+        let json = fs::read(path)?;
+        serde_json::from_slice(&json).map_err(|_| KeystoreError::Malformed)
+    }
+
+    // ---
+    // Private methods
+    // ---
+
+    fn cipher(passphrase: &str, salt: &[u8], rounds: u32) -> ChaCha20Poly1305 {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, rounds, &mut key);
+        ChaCha20Poly1305::new(Key::from_slice(&key))
+    }
+}
+
+#[derive(Debug)]
+pub enum KeystoreError {
+    Io(io::Error),
+    Malformed,
+    UnsupportedVersion(u8),
+    WrongPassphrase,
+    AddressMismatch,
+}
+
+impl fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "keystore io error: {err}"),
+            Self::Malformed => write!(f, "keystore is malformed"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported keystore version {version}")
+            }
+            Self::WrongPassphrase => write!(f, "wrong passphrase or corrupted keystore"),
+            Self::AddressMismatch => write!(f, "keystore address does not match its key"),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+impl From<io::Error> for KeystoreError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
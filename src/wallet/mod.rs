@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use ed25519_dalek::{Signer, SigningKey};
+use rand_core::OsRng;
+
+use crate::blockchain::transaction::Transaction;
+
+mod address;
+mod keystore;
+
+pub use address::{Address, AddressError, ADDRESS_PREFIX, ADDRESS_VERSION};
+pub use keystore::{Keystore, KeystoreError};
+
+/// Ed25519 keypair together with the address derived from its public key.
+pub struct Wallet {
+    key: SigningKey,
+    address: Address,
+}
+
+impl Wallet {
+    pub fn generate() -> Self {
+        Wallet::from_secret(&SigningKey::generate(&mut OsRng).to_bytes())
+    }
+
+    pub fn from_secret(secret: &[u8; 32]) -> Self {
+        Wallet::from_secret_with_prefix(secret, ADDRESS_PREFIX)
+    }
+
+    /// Wallet with its address on the network whose addresses start with
+    /// `prefix`.
+    pub fn from_secret_with_prefix(secret: &[u8; 32], prefix: &str) -> Self {
+        let key = SigningKey::from_bytes(secret);
+        let address = Address::with_prefix(&key.verifying_key(), prefix);
+        Wallet { key, address }
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Hex encoded public key, as submitted alongside signed transactions.
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key.verifying_key().as_bytes())
+    }
+
+    /// Hex encoded signature over the transaction's data, type and timestamp,
+    /// valid on the chain with id `chain_id` only.
+    pub fn sign(&self, tx: &Transaction, chain_id: &str) -> String {
+        hex::encode(self.key.sign(&tx.signing_payload(chain_id)).to_bytes())
+    }
+
+    pub fn to_keystore(&self, passphrase: &str) -> Keystore {
+        Keystore::encrypt(&self.key.to_bytes(), self.address.clone(), passphrase)
+    }
+
+    pub fn from_keystore(keystore: &Keystore, passphrase: &str) -> Result<Self, KeystoreError> {
+        let secret = keystore.decrypt(passphrase)?;
+        let wallet = Wallet::from_secret_with_prefix(&secret, keystore.address.prefix());
+
+        if wallet.address != keystore.address {
+            return Err(KeystoreError::AddressMismatch);
+        }
+        Ok(wallet)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, passphrase: &str) -> Result<(), KeystoreError> {
+        self.to_keystore(passphrase).save(path)
+    }
+
+    pub fn load(path: impl AsRef<Path>, passphrase: &str) -> Result<Self, KeystoreError> {
+        Wallet::from_keystore(&Keystore::load(path)?, passphrase)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::{
+        amount::Amount, chain::Chain, models::TransactionData, transaction::TransactionType,
+    };
+
+    #[test]
+    fn generate_wallet() {
+        let wallet = Wallet::generate();
+        let other = Wallet::generate();
+
+        assert_ne!(wallet.address(), other.address());
+        assert_eq!(
+            wallet.address().as_str().parse::<Address>().unwrap(),
+            *wallet.address()
+        );
+    }
+
+    #[test]
+    fn sign_transaction() {
+        let wallet = Wallet::generate();
+        let tx_data = TransactionData::TransferData {
+            sender: wallet.address().to_string(),
+            receiver: Wallet::generate().address().to_string(),
+            amount: Amount::from_coins(1).unwrap(),
+            fee: Amount::ZERO,
+            nonce: 0,
+        };
+        let tx = Chain::new_transaction(tx_data, TransactionType::Transfer);
+
+        let signature = wallet.sign(&tx, "test");
+        assert!(tx
+            .verify("test", ADDRESS_PREFIX, &wallet.public_key(), &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn save_and_load_keystore() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.json");
+        let wallet = Wallet::generate();
+
+        wallet.save(&path, "correct horse").unwrap();
+        let loaded = Wallet::load(&path, "correct horse").unwrap();
+
+        assert_eq!(loaded.address(), wallet.address());
+        assert_eq!(loaded.public_key(), wallet.public_key());
+
+        assert!(matches!(
+            Wallet::load(&path, "wrong horse"),
+            Err(KeystoreError::WrongPassphrase)
+        ));
+
+        // Keeping the network of the address
+        let wallet = Wallet::from_secret_with_prefix(&[3u8; 32], "rt");
+        wallet.save(&path, "correct horse").unwrap();
+        let loaded = Wallet::load(&path, "correct horse").unwrap();
+        assert_eq!(loaded.address(), wallet.address());
+    }
+
+    #[test]
+    fn keystore_address_mismatch() {
+        let wallet = Wallet::generate();
+        let mut keystore = wallet.to_keystore("passphrase");
+        keystore.address = Wallet::generate().address().clone();
+
+        assert!(matches!(
+            Wallet::from_keystore(&keystore, "passphrase"),
+            Err(KeystoreError::AddressMismatch)
+        ));
+    }
+}
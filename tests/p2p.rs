@@ -0,0 +1,219 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_chain::blockchain::{
+    amount::Amount, block::Block, chain::Chain, config::ChainConfig, mempool::MempoolConfig,
+    models::TransactionData, spec::ChainSpec, transaction::TransactionType,
+};
+use rust_chain::miner::Miner;
+use rust_chain::p2p::{Node, NodeConfig, PeerError, MAX_HEADERS_PER_MESSAGE};
+use rust_chain::storage::{BlockStore, MemoryStore};
+use rust_chain::wallet::Wallet;
+
+fn wallet() -> Wallet {
+    Wallet::from_secret(&[7u8; 32])
+}
+
+fn config() -> ChainConfig {
+    let mut spec = ChainSpec::test(
+        wallet().address().to_string(),
+        Amount::from_coins(10).unwrap(),
+    );
+    spec.genesis.difficulty = 1;
+    spec.consensus.retarget_window = 0;
+    spec.config(MempoolConfig::default())
+}
+
+/// Chain starting from `genesis`, or from a fresh genesis block if not given.
+fn chain(genesis: Option<&Block>) -> Arc<Mutex<Chain>> {
+    let mut store = MemoryStore::new();
+    if let Some(genesis) = genesis {
+        store.append_block(genesis.clone()).unwrap();
+    }
+    let chain = Chain::new(config(), wallet().address(), Box::new(store));
+    Arc::new(Mutex::new(chain))
+}
+
+fn start(chain: &Arc<Mutex<Chain>>, seeds: &[&Node]) -> Node {
+    let mut config = NodeConfig::new(SocketAddr::from(([127, 0, 0, 1], 0)));
+    config.seeds = seeds.iter().map(|seed| seed.local_addr()).collect();
+    Node::start(config, chain.clone()).unwrap()
+}
+
+fn wait_until(what: &str, condition: impl Fn() -> bool) {
+    let started = Instant::now();
+    while !condition() {
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "timed out waiting until {what}"
+        );
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn tip(chain: &Mutex<Chain>) -> String {
+    chain.lock().unwrap().last_block().hash()
+}
+
+#[test]
+fn gossip_between_nodes() {
+    let chain_a = chain(None);
+    let genesis = chain_a.lock().unwrap().last_block().clone();
+    let (chain_b, chain_c) = (chain(Some(&genesis)), chain(Some(&genesis)));
+
+    // C only knows B, and finds A through B's peer list
+    let node_a = start(&chain_a, &[]);
+    let node_b = start(&chain_b, &[&node_a]);
+    wait_until("B is connected", || node_b.peers().len() == 1);
+    let node_c = start(&chain_c, &[&node_b]);
+    for node in [&node_a, &node_b, &node_c] {
+        wait_until("every node is connected", || node.peers().len() == 2);
+    }
+
+    // A transaction added on A reaches every mempool
+    let wallet = wallet();
+    let data = TransactionData::TransferData {
+        sender: wallet.address().to_string(),
+        receiver: "you".to_string(),
+        amount: Amount::from_coins(1).unwrap(),
+        fee: Amount::ZERO,
+        nonce: 0,
+    };
+    let mut tx = Chain::new_transaction(data, TransactionType::Transfer);
+    let signature = wallet.sign(&tx, "test");
+    chain_a
+        .lock()
+        .unwrap()
+        .add_transaction(&mut tx, &wallet.public_key(), &signature)
+        .unwrap();
+    let tx_hash = tx.hash.to_string();
+    for chain in [&chain_b, &chain_c] {
+        wait_until("the transaction is relayed", || {
+            chain.lock().unwrap().mempool().contains(&tx_hash)
+        });
+    }
+
+    // A block mined on C is added by every node, confirming the transaction
+    chain_c.lock().unwrap().mine_new_block().unwrap();
+    let mined = tip(&chain_c);
+    for chain in [&chain_a, &chain_b] {
+        wait_until("the block is relayed", || tip(chain) == mined);
+        let chain = chain.lock().unwrap();
+        assert_eq!(chain.height(), 2);
+        assert!(chain.mempool().is_empty());
+        assert!(chain.get_transaction(&tx_hash).is_some());
+    }
+    wait_until("peers learn the new height", || {
+        node_a.peers().iter().all(|peer| peer.height == 2)
+    });
+
+    // A node joining later catches up on the blocks it missed
+    let chain_d = chain(Some(&genesis));
+    let node_d = start(&chain_d, &[&node_a]);
+    wait_until("D catches up", || tip(&chain_d) == mined);
+    wait_until("D finds every node", || node_d.peers().len() == 3);
+
+    // Nodes drop out of the peer list once they disconnect
+    node_d.shutdown();
+    wait_until("D is disconnected", || {
+        [&node_a, &node_b, &node_c]
+            .iter()
+            .all(|node| node.peers().len() == 2)
+    });
+}
+
+#[test]
+fn sync_from_several_peers() {
+    // More blocks than fit in one message of headers
+    let chain_a = chain(None);
+    let height = MAX_HEADERS_PER_MESSAGE + 20;
+    let miner = Miner::new(1);
+    for _ in 1..height {
+        let template = chain_a.lock().unwrap().block_template();
+        let block = miner.mine(template).block.unwrap();
+        chain_a.lock().unwrap().submit_block(block).unwrap();
+    }
+    let blocks: Vec<Block> = chain_a
+        .lock()
+        .unwrap()
+        .blocks()
+        .into_iter()
+        .cloned()
+        .collect();
+    let mut store = MemoryStore::new();
+    for block in &blocks {
+        store.append_block(block.clone()).unwrap();
+    }
+    let chain_b = Arc::new(Mutex::new(Chain::new(
+        config(),
+        wallet().address(),
+        Box::new(store),
+    )));
+
+    let node_a = start(&chain_a, &[]);
+    let node_b = start(&chain_b, &[]);
+    let chain_c = chain(Some(&blocks[0]));
+    let node_c = start(&chain_c, &[&node_a, &node_b]);
+
+    let tip = blocks[height - 1].hash();
+    wait_until("C catches up", || self::tip(&chain_c) == tip);
+    wait_until("C is done syncing", || !node_c.sync_status().syncing);
+    let status = node_c.sync_status();
+    assert_eq!(status.height, height);
+    assert_eq!(status.best_height, height);
+    assert_eq!(status.blocks_in_flight, 0);
+    assert!(chain_c.lock().unwrap().validate().valid);
+}
+
+#[test]
+fn switch_to_heavier_branch() {
+    let chain_a = chain(None);
+    let genesis = chain_a.lock().unwrap().last_block().clone();
+    let chain_b = chain(Some(&genesis));
+
+    // Apart, A mines one block and B mines two paying another miner
+    let miner = Miner::new(1);
+    let mine = |chain: &Mutex<Chain>, coinbase: &Wallet| {
+        let template = chain.lock().unwrap().block_template_for(coinbase.address());
+        let block = miner.mine(template).block.unwrap();
+        chain.lock().unwrap().submit_block(block).unwrap();
+    };
+    let other = Wallet::from_secret(&[8u8; 32]);
+    mine(&chain_a, &wallet());
+    mine(&chain_b, &other);
+    mine(&chain_b, &other);
+
+    let node_a = start(&chain_a, &[]);
+    let _node_b = start(&chain_b, &[&node_a]);
+
+    let heavier = tip(&chain_b);
+    wait_until("A switches to B's branch", || tip(&chain_a) == heavier);
+    let chain_a = chain_a.lock().unwrap();
+    assert_eq!(chain_a.height(), 3);
+    assert_eq!(chain_a.work(), chain_b.lock().unwrap().work());
+    assert!(chain_a.validate().valid);
+}
+
+#[test]
+fn refuse_other_chains() {
+    let chain_a = chain(None);
+    let node_a = start(&chain_a, &[]);
+
+    // Same settings, but a genesis block of its own
+    let mut config = config();
+    config.genesis.chain_id = "other".to_string();
+    let chain_b = Chain::new(config, wallet().address(), Box::new(MemoryStore::new()));
+    let chain_b = Arc::new(Mutex::new(chain_b));
+    assert_ne!(tip(&chain_a), tip(&chain_b));
+    let node_b = start(&chain_b, &[]);
+
+    let err = node_b.connect(node_a.local_addr()).unwrap_err();
+    assert!(matches!(err, PeerError::ChainMismatch { .. }));
+    assert!(node_b.peers().is_empty());
+
+    let err = node_a.connect(node_a.local_addr()).unwrap_err();
+    assert!(matches!(err, PeerError::SelfConnection));
+    assert!(node_a.peers().is_empty());
+}